@@ -15,6 +15,9 @@ pub enum Error {
     #[error("author field error: {0}")]
     AuthorField(#[from] AuthorFieldError),
 
+    #[error("author parse error: {0}")]
+    AuthorParse(#[from] AuthorParseError),
+
     #[error("get error: {0}")]
     Get(#[from] GetError),
 
@@ -26,6 +29,36 @@ pub enum Error {
 
     #[error("replace error: {0}")]
     Replace(#[from] ReplaceError),
+
+    #[error("profile error: {0}")]
+    Profile(#[from] ProfileError),
+
+    #[error("rule error: {0}")]
+    Rule(#[from] RuleError),
+
+    #[error("output error: {0}")]
+    Output(#[from] OutputError),
+
+    #[error("journal error: {0}")]
+    Journal(#[from] JournalError),
+
+    #[error("map error: {0}")]
+    Map(#[from] MapError),
+
+    #[error("regex error: {0}")]
+    Regex(#[from] regex::Error),
+
+    #[error("filter parse error: {0}")]
+    FilterParse(#[from] FilterParseError),
+
+    #[error("template error: {0}")]
+    Template(#[from] TemplateError),
+
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 #[derive(Debug, Error)]
@@ -90,6 +123,9 @@ pub enum ConditionTextError {
     #[error("committer has None field: {0}")]
     CommitterHasNoneField(#[from] CommitterHasNoneField),
 
+    #[error("{0} is not supported by filter-branch")]
+    UnsupportedByFilterBranch(String),
+
     #[error("InvalidArguments: {0}")]
     InvalidArguments(#[from] InvalidArguments),
 }
@@ -114,6 +150,9 @@ pub enum GetError {
     /// Error returned when using "addr".
     #[error("Addr error: {0}")]
     Addr(#[from] addr::Error),
+
+    #[error("invalid ident: {0}")]
+    InvalidIdent(String),
 }
 
 #[derive(Debug, Error)]
@@ -121,12 +160,18 @@ pub enum SetError {
     #[error("author field error: {0}")]
     AuthorField(#[from] AuthorFieldError),
 
+    #[error("extensions.worktreeConfig is not enabled")]
+    WorktreeConfigDisabled,
+
     #[error("output error: {0}")]
     Output(#[from] OutputError),
 }
 
 #[derive(Debug, Error)]
 pub enum UnsetError {
+    #[error("extensions.worktreeConfig is not enabled")]
+    WorktreeConfigDisabled,
+
     #[error("Command execute error: {0}")]
     CommandExecute(#[from] CommandExecuteError),
 
@@ -150,4 +195,143 @@ pub enum ReplaceError {
 
     #[error("output error: {0}")]
     Output(#[from] OutputError),
+
+    #[error("journal error: {0}")]
+    Journal(#[from] JournalError),
+
+    #[error("{0} is not supported by filter-branch")]
+    UnsupportedByFilterBranch(String),
+}
+
+#[derive(Debug, Error)]
+pub enum ProfileError {
+    #[error("profile `{0}` is not found")]
+    NotFound(String),
+
+    #[error("profile `{0}` already exists")]
+    AlreadyExists(String),
+
+    #[error("invalid profile name: `{0}`")]
+    InvalidName(String),
+
+    #[error("config directory is not found")]
+    ConfigDirNotFound,
+
+    #[error("author field error: {0}")]
+    AuthorField(#[from] AuthorFieldError),
+
+    #[error("InvalidArguments: {0}")]
+    InvalidArguments(#[from] InvalidArguments),
+
+    /// Error returned when using "addr".
+    #[error("Addr error: {0}")]
+    Addr(#[from] addr::Error),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("toml deserialize error: {0}")]
+    TomlDeserialize(#[from] toml::de::Error),
+
+    #[error("toml serialize error: {0}")]
+    TomlSerialize(#[from] toml::ser::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum RuleError {
+    #[error("rule for `gitdir:{0}` is not found")]
+    NotFound(String),
+
+    #[error("config directory is not found")]
+    ConfigDirNotFound,
+
+    #[error("set error: {0}")]
+    Set(#[from] SetError),
+
+    #[error("output error: {0}")]
+    Output(#[from] OutputError),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum JournalError {
+    #[error("there is no replacement to undo")]
+    NothingToUndo,
+
+    #[error("output error: {0}")]
+    Output(#[from] OutputError),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("toml deserialize error: {0}")]
+    TomlDeserialize(#[from] toml::de::Error),
+
+    #[error("toml serialize error: {0}")]
+    TomlSerialize(#[from] toml::ser::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum MapError {
+    #[error("mapping {index}: {source}")]
+    InvalidEntry { index: usize, source: addr::Error },
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("toml deserialize error: {0}")]
+    TomlDeserialize(#[from] toml::de::Error),
+
+    #[error("toml serialize error: {0}")]
+    TomlSerialize(#[from] toml::ser::Error),
+
+    #[error("csv error: {0}")]
+    Csv(#[from] csv::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum AuthorParseError {
+    #[error("`{0}` is not `Name <email>`")]
+    InvalidFormat(String),
+
+    #[error("`{0}` is not `<seconds since the epoch> <+hhmm or -hhmm>`")]
+    InvalidDate(String),
+
+    #[error("addr error: {0}")]
+    Addr(#[from] addr::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum FilterParseError {
+    #[error("unexpected `{0}`")]
+    Unexpected(String),
+
+    #[error("unexpected end of the expression")]
+    UnexpectedEnd,
+
+    #[error("unknown field `{0}`")]
+    UnknownField(String),
+
+    #[error("unterminated quote")]
+    UnterminatedQuote,
+
+    #[error("regex error: {0}")]
+    Regex(#[from] regex::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("unknown placeholder `{{{0}}}`. Use `{{name}}`, `{{email}}`, `{{localpart}}` or `{{domain}}`")]
+    UnknownPlaceholder(String),
+
+    #[error("unmatched `{{` or `}}`. Write `{{{{` or `}}}}` for a literal brace")]
+    UnmatchedBrace,
+
+    #[error("neither name nor email is replaced")]
+    Empty,
+
+    #[error("{0:?} cannot be used in a name or an email")]
+    InvalidCharacter(char),
 }
@@ -1,16 +1,56 @@
 //! A module that defines functions and structures for calling git commands.
 
 mod author;
+mod commit;
+mod commit_iter;
+mod config_entry;
 mod config_file_location;
+mod effective_identity;
+mod fast_export;
+mod filter_expression;
+mod git_context;
+mod ident;
+mod identity_cluster;
+mod identity_matcher;
+mod identity_stats;
+mod identity_template;
+mod include_rule;
+mod mailmap;
+mod profile;
 mod replace_filter;
+mod replace_journal;
+mod replace_map;
 mod replace_target;
+mod revision_spec;
+mod rewrite_engine;
+mod role;
+mod tag;
 mod user_parameter;
 
 use crate::error::*;
 pub use author::Author;
+pub use commit::{Commit, ReplacedCommit};
+pub use commit_iter::CommitIter;
+pub use config_entry::{AuthorConfigEntries, ConfigEntry, ConfigOrigin};
 pub use config_file_location::ConfigFileLocation;
+pub use effective_identity::{EffectiveIdentity, IdentitySource, IdentitySources};
+pub use git_context::GitContext;
+pub use ident::Ident;
+pub use identity_cluster::IdentityCluster;
+pub use identity_matcher::IdentityMatcher;
+pub use identity_stats::IdentityStats;
+pub use identity_template::IdentityTemplate;
+pub use include_rule::IncludeRule;
+pub use mailmap::{Mailmap, MailmapEntry};
+pub use profile::{Profile, ProfileStore};
 pub use replace_filter::ReplaceFilter;
+pub use replace_journal::{JournalEntry, RefUpdate, ReplaceJournal};
+pub use replace_map::{MapScope, ReplaceMap, ReplaceMapEntry};
 pub use replace_target::ReplaceTarget;
+pub use revision_spec::RevisionSpec;
+pub use rewrite_engine::RewriteEngine;
+pub use role::Role;
+pub use tag::{ReplacedTag, Tag};
 use user_parameter::UserParameter;
 
 fn output_to_result(output: std::process::Output) -> Result<String, OutputError> {
@@ -25,21 +65,17 @@ fn output_to_result(output: std::process::Output) -> Result<String, OutputError>
 
 /// get user.name or user.email
 fn get_git_user_param(
-    location: Option<ConfigFileLocation>,
+    context: &GitContext,
+    location: Option<&ConfigFileLocation>,
+    role: Role,
     user_parameter: UserParameter,
 ) -> Result<Option<String>, OutputError> {
-    let output = if let Some(location) = location {
-        std::process::Command::new("git")
-            .arg("config")
-            .arg(location.to_arg())
-            .arg(user_parameter.to_arg())
-            .output()?
-    } else {
-        std::process::Command::new("git")
-            .arg("config")
-            .arg(user_parameter.to_arg())
-            .output()?
-    };
+    let output = context
+        .command()
+        .arg("config")
+        .args(location.map(|l| l.to_args()).unwrap_or_default())
+        .arg(user_parameter.to_arg(role))
+        .output()?;
     if output.status.success() {
         let s = String::from_utf8(output.stdout)?;
         Ok(Some(s.trim_end_matches('\n').to_string()))
@@ -51,23 +87,187 @@ fn get_git_user_param(
 }
 
 /// get author(user.name and email)
-pub fn get_author(location: Option<ConfigFileLocation>) -> Result<Author, GetError> {
-    let name = get_git_user_param(location, UserParameter::Name)?;
-    let email = get_git_user_param(location, UserParameter::Email)?;
+///
+/// `role` selects `user.*`, `author.*` or `committer.*`.
+pub fn get_author(
+    context: &GitContext,
+    location: Option<&ConfigFileLocation>,
+    role: Role,
+) -> Result<Author, GetError> {
+    let name = get_git_user_param(context, location, role, UserParameter::Name)?;
+    let email = get_git_user_param(context, location, role, UserParameter::Email)?;
     let author = Author::new(name, email)?;
     Ok(author)
 }
 
+/// get the top level directory of the working tree
+fn get_toplevel(context: &GitContext) -> Option<std::path::PathBuf> {
+    let output = context
+        .command()
+        .arg("rev-parse")
+        .arg("--show-toplevel")
+        .output()
+        .ok()?;
+    let stdout = output_to_result(output).ok()?;
+    Some(stdout.trim_end_matches('\n').into())
+}
+
+/// get all values of `key` with the scope and the origin
+fn get_config_entries(
+    context: &GitContext,
+    location: Option<&ConfigFileLocation>,
+    key: &str,
+) -> Result<Vec<ConfigEntry>, OutputError> {
+    let output = context
+        .command()
+        .arg("config")
+        .args(location.map(|l| l.to_args()).unwrap_or_default())
+        .arg("-z")
+        .arg("--show-origin")
+        .arg("--show-scope")
+        .arg("--get-all")
+        .arg(key)
+        .output()?;
+    if output.status.success() {
+        let s = String::from_utf8(output.stdout)?;
+        let toplevel = get_toplevel(context);
+        Ok(ConfigEntry::parse_all(&s, key, toplevel.as_deref()))
+    } else if output.stderr.is_empty() {
+        Ok(vec![])
+    } else {
+        Err(CommandExecuteError(String::from_utf8(output.stderr)?).into())
+    }
+}
+
+/// get all user.name or user.email values with the scope and the origin
+fn get_git_user_param_entries(
+    context: &GitContext,
+    location: Option<&ConfigFileLocation>,
+    role: Role,
+    user_parameter: UserParameter,
+) -> Result<Vec<ConfigEntry>, OutputError> {
+    get_config_entries(context, location, &user_parameter.to_arg(role))
+}
+
+/// get all user.name and user.email values with the scope and the origin
+pub fn get_author_entries(
+    context: &GitContext,
+    location: Option<&ConfigFileLocation>,
+    role: Role,
+) -> Result<AuthorConfigEntries, GetError> {
+    Ok(AuthorConfigEntries::new(
+        get_git_user_param_entries(context, location, role, UserParameter::Name)?,
+        get_git_user_param_entries(context, location, role, UserParameter::Email)?,
+    ))
+}
+
+/// get a value of `git var`
+fn get_git_var(context: &GitContext, variable: &str) -> Result<String, OutputError> {
+    let output = context.command().arg("var").arg(variable).output()?;
+    let value = output_to_result(output)?;
+    Ok(value.trim_end_matches('\n').to_string())
+}
+
+/// get an identity with `git var`. e.g. `GIT_AUTHOR_IDENT`
+///
+/// The identity is not validated again because it is what git uses.
+fn get_git_var_ident(context: &GitContext, variable: &str) -> Result<Author, GetError> {
+    let ident = get_git_var(context, variable)?;
+    match ident.parse::<Ident>() {
+        Ok(parsed) => Ok(parsed.into_author()),
+        Err(_) => Err(GetError::InvalidIdent(ident)),
+    }
+}
+
+/// find where a name or an email of the identity comes from in the same order as git
+///
+/// 1. environment variable. e.g. `GIT_AUTHOR_NAME`
+/// 2. `<role>.name` or `<role>.email`
+/// 3. `user.name` or `user.email`
+/// 4. `EMAIL` environment variable (email only)
+fn get_identity_source(
+    context: &GitContext,
+    variable: &str,
+    role: Role,
+    user_parameter: UserParameter,
+) -> Result<IdentitySource, OutputError> {
+    if context.env_var(variable).is_some() {
+        return Ok(IdentitySource::Environment(variable.to_string()));
+    }
+    for role in &[role, Role::User] {
+        if let Some(entry) = get_git_user_param_entries(context, None, *role, user_parameter)?.pop()
+        {
+            return Ok(IdentitySource::Config {
+                key: user_parameter.to_arg(*role),
+                entry,
+            });
+        }
+    }
+    let email_variable = "EMAIL";
+    match user_parameter {
+        UserParameter::Email
+            if context
+                .env_var(email_variable)
+                .is_some_and(|v| !v.is_empty()) =>
+        {
+            Ok(IdentitySource::Environment(email_variable.to_string()))
+        }
+        _ => Ok(IdentitySource::System),
+    }
+}
+
+/// Get the author and the committer which git uses for a new commit,
+/// and where the each values come from.
+pub fn get_effective_identity(context: &GitContext) -> Result<EffectiveIdentity, GetError> {
+    let author = get_git_var_ident(context, "GIT_AUTHOR_IDENT")?;
+    let committer = get_git_var_ident(context, "GIT_COMMITTER_IDENT")?;
+    let sources = IdentitySources {
+        author_name: get_identity_source(
+            context,
+            "GIT_AUTHOR_NAME",
+            Role::Author,
+            UserParameter::Name,
+        )?,
+        author_email: get_identity_source(
+            context,
+            "GIT_AUTHOR_EMAIL",
+            Role::Author,
+            UserParameter::Email,
+        )?,
+        committer_name: get_identity_source(
+            context,
+            "GIT_COMMITTER_NAME",
+            Role::Committer,
+            UserParameter::Name,
+        )?,
+        committer_email: get_identity_source(
+            context,
+            "GIT_COMMITTER_EMAIL",
+            Role::Committer,
+            UserParameter::Email,
+        )?,
+    };
+
+    Ok(EffectiveIdentity {
+        author,
+        committer,
+        sources,
+    })
+}
+
 /// set user.name or user.email
 fn set_git_user_param(
-    location: ConfigFileLocation,
+    context: &GitContext,
+    location: &ConfigFileLocation,
+    role: Role,
     user_parameter: UserParameter,
     value: &str,
 ) -> Result<String, OutputError> {
-    let output = std::process::Command::new("git")
+    let output = context
+        .command()
         .arg("config")
-        .arg(location.to_arg())
-        .arg(user_parameter.to_arg())
+        .args(location.to_args())
+        .arg(user_parameter.to_arg(role))
         .arg(value)
         .output()?;
 
@@ -81,36 +281,42 @@ fn set_git_user_param(
 }
 
 /// Set git author
-pub fn set_author(location: ConfigFileLocation, author: &Author) -> Result<(), SetError> {
+///
+/// `role` selects `user.*`, `author.*` or `committer.*`.
+/// If `location` is `ConfigFileLocation::Worktree`, `extensions.worktreeConfig` must be enabled.
+pub fn set_author(
+    context: &GitContext,
+    location: &ConfigFileLocation,
+    role: Role,
+    author: &Author,
+) -> Result<(), SetError> {
+    if *location == ConfigFileLocation::Worktree && !is_worktree_config_enabled(context)? {
+        return Err(SetError::WorktreeConfigDisabled);
+    }
     match (author.name(), author.email()) {
         (Some(name), Some(email)) => {
-            set_git_user_param(location, UserParameter::Name, name)?;
-            set_git_user_param(location, UserParameter::Email, email)?;
+            set_git_user_param(context, location, role, UserParameter::Name, name)?;
+            set_git_user_param(context, location, role, UserParameter::Email, email)?;
             Ok(())
         }
-        _ => Err(AuthorFieldError::new(&author).unwrap().into()),
+        _ => Err(AuthorFieldError::new(author).unwrap().into()),
     }
 }
 
 /// unset user.name or user.email
 fn unset_git_user_param(
-    location: Option<ConfigFileLocation>,
+    context: &GitContext,
+    location: Option<&ConfigFileLocation>,
+    role: Role,
     user_parameter: UserParameter,
 ) -> Result<(), OutputError> {
-    let output = if let Some(location) = location {
-        std::process::Command::new("git")
-            .arg("config")
-            .arg(location.to_arg())
-            .arg("--unset")
-            .arg(user_parameter.to_arg())
-            .output()?
-    } else {
-        std::process::Command::new("git")
-            .arg("config")
-            .arg("--unset")
-            .arg(user_parameter.to_arg())
-            .output()?
-    };
+    let output = context
+        .command()
+        .arg("config")
+        .args(location.map(|l| l.to_args()).unwrap_or_default())
+        .arg("--unset")
+        .arg(user_parameter.to_arg(role))
+        .output()?;
 
     if output.status.success() || output.stderr.is_empty() {
         Ok(())
@@ -121,80 +327,1359 @@ fn unset_git_user_param(
 }
 
 /// Unset the author parameters.
-pub fn unset_author(location: Option<ConfigFileLocation>) -> Result<(), UnsetError> {
-    unset_git_user_param(location, UserParameter::Name)?;
-    unset_git_user_param(location, UserParameter::Email)?;
+///
+/// `role` selects `user.*`, `author.*` or `committer.*`.
+/// If `location` is `ConfigFileLocation::Worktree`, `extensions.worktreeConfig` must be enabled.
+pub fn unset_author(
+    context: &GitContext,
+    location: Option<&ConfigFileLocation>,
+    role: Role,
+) -> Result<(), UnsetError> {
+    if location == Some(&ConfigFileLocation::Worktree) && !is_worktree_config_enabled(context)? {
+        return Err(UnsetError::WorktreeConfigDisabled);
+    }
+    unset_git_user_param(context, location, role, UserParameter::Name)?;
+    unset_git_user_param(context, location, role, UserParameter::Email)?;
+    Ok(())
+}
+
+/// Whether `extensions.worktreeConfig` is enabled in the repository of `context`.
+pub fn is_worktree_config_enabled(context: &GitContext) -> Result<bool, OutputError> {
+    let output = context
+        .command()
+        .arg("config")
+        .arg("--bool")
+        .arg("extensions.worktreeConfig")
+        .output()?;
+    if output.status.success() {
+        Ok(String::from_utf8(output.stdout)?.trim() == "true")
+    } else if output.stderr.is_empty() {
+        Ok(false)
+    } else {
+        Err(CommandExecuteError(String::from_utf8(output.stderr)?).into())
+    }
+}
+
+/// Enable `extensions.worktreeConfig` in the repository of `context`
+/// so that `ConfigFileLocation::Worktree` can be used.
+pub fn enable_worktree_config(context: &GitContext) -> Result<(), OutputError> {
+    let output = context
+        .command()
+        .arg("config")
+        .args(ConfigFileLocation::Local.to_args())
+        .arg("extensions.worktreeConfig")
+        .arg("true")
+        .output()?;
+    output_to_result(output)?;
     Ok(())
 }
 
-/// Replaces committer and author from past commits in the current branch.
+/// get `includeIf.*.path` entries of the global config file
+fn get_global_include_entries(context: &GitContext) -> Result<Vec<(String, String)>, OutputError> {
+    let output = context
+        .command()
+        .arg("config")
+        .args(ConfigFileLocation::Global.to_args())
+        .arg("-z")
+        .arg("--get-regexp")
+        .arg(r"^includeif\..*\.path$")
+        .output()?;
+    if !output.status.success() {
+        return if output.stderr.is_empty() {
+            Ok(vec![])
+        } else {
+            Err(CommandExecuteError(String::from_utf8(output.stderr)?).into())
+        };
+    }
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let entries = stdout
+        .split('\0')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.find('\n') {
+            Some(i) => (entry[..i].to_string(), entry[i + 1..].to_string()),
+            None => (entry.to_string(), String::new()),
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// escape `s` so that it can be used as a value regex of `git config`
+fn escape_regex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if r"\.^$|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// write user.name and user.email to the identity file of the rule
+fn write_include_file(
+    context: &GitContext,
+    rule: &IncludeRule,
+    author: &Author,
+) -> Result<(), RuleError> {
+    if let Some(dir) = rule.path().parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    set_author(
+        context,
+        &ConfigFileLocation::File(rule.path().to_path_buf()),
+        Role::User,
+        author,
+    )?;
+    Ok(())
+}
+
+/// Get the includeIf rules created by git-author.
+pub fn include_rules(context: &GitContext) -> Result<Vec<IncludeRule>, RuleError> {
+    let dir = IncludeRule::default_dir().ok_or(RuleError::ConfigDirNotFound)?;
+    let rules = get_global_include_entries(context)?
+        .iter()
+        .filter_map(|(key, value)| IncludeRule::from_config_entry(key, value, &dir))
+        .collect();
+    Ok(rules)
+}
+
+/// Write the identity file of `profile` and include it from the global config file
+/// with `[includeIf "gitdir:<gitdir>"]`.
+///
+/// If a rule for `gitdir` already exists, it is replaced.
+/// includeIf entries which were not created by git-author are left as they are.
+pub fn add_include_rule(
+    context: &GitContext,
+    gitdir: &str,
+    profile: &Profile,
+) -> Result<IncludeRule, RuleError> {
+    let dir = IncludeRule::default_dir().ok_or(RuleError::ConfigDirNotFound)?;
+    let rule = IncludeRule::new(gitdir, profile.name(), &dir);
+    write_include_file(context, &rule, profile.author())?;
+
+    let old_rules: Vec<_> = include_rules(context)?
+        .into_iter()
+        .filter(|r| r.gitdir() == rule.gitdir())
+        .collect();
+    if old_rules.contains(&rule) {
+        return Ok(rule);
+    }
+
+    for old_rule in &old_rules {
+        unset_include_rule(context, old_rule)?;
+    }
+    let output = context
+        .command()
+        .arg("config")
+        .args(ConfigFileLocation::Global.to_args())
+        .arg("--add")
+        .arg(rule.to_key())
+        .arg(rule.path())
+        .output()?;
+    output_to_result(output)?;
+    remove_unused_include_files(context, &old_rules)?;
+
+    Ok(rule)
+}
+
+/// Remove the includeIf rule for `gitdir` from the global config file.
+pub fn remove_include_rule(
+    context: &GitContext,
+    gitdir: &str,
+) -> Result<Vec<IncludeRule>, RuleError> {
+    let rules: Vec<_> = include_rules(context)?
+        .into_iter()
+        .filter(|r| r.gitdir() == gitdir)
+        .collect();
+    if rules.is_empty() {
+        return Err(RuleError::NotFound(gitdir.to_string()));
+    }
+
+    for rule in &rules {
+        unset_include_rule(context, rule)?;
+    }
+    remove_unused_include_files(context, &rules)?;
+    Ok(rules)
+}
+
+/// Rewrite the identity file of `profile` if some rules include it.
+/// Returns `true` if the file was rewritten.
+pub fn update_include_file(context: &GitContext, profile: &Profile) -> Result<bool, RuleError> {
+    match include_rules(context)?
+        .into_iter()
+        .find(|r| r.profile() == profile.name())
+    {
+        Some(rule) => {
+            write_include_file(context, &rule, profile.author())?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// remove only the entry of the rule from the includeIf section
+fn unset_include_rule(context: &GitContext, rule: &IncludeRule) -> Result<(), RuleError> {
+    let output = context
+        .command()
+        .arg("config")
+        .args(ConfigFileLocation::Global.to_args())
+        .arg("--unset-all")
+        .arg(rule.to_key())
+        .arg(format!(
+            "^{}$",
+            escape_regex(&rule.path().to_string_lossy())
+        ))
+        .output()?;
+    output_to_result(output)?;
+
+    // Older git leaves the empty section header after `--unset`.
+    // Newer git removes it by itself, so the result of `--remove-section` is ignored.
+    let section_has_entries = get_global_include_entries(context)?
+        .iter()
+        .any(|(key, _)| IncludeRule::gitdir_from_key(key) == Some(rule.gitdir()));
+    if !section_has_entries {
+        context
+            .command()
+            .arg("config")
+            .args(ConfigFileLocation::Global.to_args())
+            .arg("--remove-section")
+            .arg(format!("includeIf.gitdir:{}", rule.gitdir()))
+            .output()?;
+    }
+    Ok(())
+}
+
+/// remove identity files which are no longer included by any rule
+fn remove_unused_include_files(
+    context: &GitContext,
+    rules: &[IncludeRule],
+) -> Result<(), RuleError> {
+    let current_rules = include_rules(context)?;
+    for rule in rules {
+        if rule.path().exists() && current_rules.iter().all(|r| r.path() != rule.path()) {
+            std::fs::remove_file(rule.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces committer and author from past commits in `revision`.
 /// options
 /// --author-only
 /// --committer-only
-pub fn replace(filter: ReplaceFilter, target: ReplaceTarget) -> Result<(), ReplaceError> {
-    let condition_arg = format!(
-        r#"
-        if [ {} ]; then
-            {}
-        fi
-        HEAD;"#,
-        filter.to_condition_text()?,
-        target.to_proccessing_content_text()?
-    );
+pub fn replace(
+    context: &GitContext,
+    filter: ReplaceFilter,
+    target: ReplaceTarget,
+    revision: &RevisionSpec,
+    engine: RewriteEngine,
+) -> Result<JournalEntry, ReplaceError> {
+    replace_impl(context, &[(filter, target)], revision, engine, false)
+}
 
-    replace_impl(&condition_arg)
+/// Returns the commits which `replace` rewrites without rewriting them.
+pub fn replace_dry_run(
+    context: &GitContext,
+    filter: &ReplaceFilter,
+    target: &ReplaceTarget,
+    revision: &RevisionSpec,
+) -> Result<Vec<ReplacedCommit>, ReplaceError> {
+    plan_replace(context, &[(filter.clone(), target.clone())], revision)
 }
 
-/// Replaces committer and author from past commits in the current branch.
+/// Replaces committer and author from past commits in `revision`.
 ///
 /// # Arguments
 /// * `target` - Old AUTHOR or COMMITTER
 /// * `new_author` - New AUTHOR or COMMITTER
-pub fn replace_simple(target: Author, new_author: Author) -> Result<(), ReplaceError> {
-    let condition_arg = format!(
-        r#"
-        if [ {} ]; then
-            {}
-        elif [ {} ]; then
-            {}
-        elif [ {} ]; then
-            {}
-        fi
-        HEAD;"#,
-        ReplaceFilter::AuthorAndCommitter {
-            author: target.clone(),
-            committer: target.clone()
-        }
-        .to_condition_text()?,
-        ReplaceTarget::AuthorAndCommitter {
-            new_author: new_author.clone(),
-            new_committer: new_author.clone()
-        }
-        .to_proccessing_content_text()?,
-        ReplaceFilter::AuthorOnly(target.clone()).to_condition_text()?,
-        ReplaceTarget::Author {
-            new_author: new_author.clone()
-        }
-        .to_proccessing_content_text()?,
-        ReplaceFilter::CommitterOnly(target).to_condition_text()?,
-        ReplaceTarget::Committer {
-            new_committer: new_author
-        }
-        .to_proccessing_content_text()?,
-    );
-
-    replace_impl(&condition_arg)
+/// * `revision` - Commits to rewrite
+/// * `engine` - How to rewrite the commits
+pub fn replace_simple(
+    context: &GitContext,
+    target: Author,
+    new_author: Author,
+    revision: &RevisionSpec,
+    engine: RewriteEngine,
+) -> Result<JournalEntry, ReplaceError> {
+    replace_impl(
+        context,
+        &simple_rules(&target, &new_author.into()),
+        revision,
+        engine,
+        false,
+    )
+}
+
+/// Returns the commits which `replace_simple` rewrites without rewriting them.
+pub fn replace_simple_dry_run(
+    context: &GitContext,
+    target: &Author,
+    new_author: &Author,
+    revision: &RevisionSpec,
+) -> Result<Vec<ReplacedCommit>, ReplaceError> {
+    plan_replace(
+        context,
+        &simple_rules(target, &new_author.clone().into()),
+        revision,
+    )
+}
+
+/// Replaces committers and authors from past commits in `revision` with the rules in one rewrite.
+///
+/// The rules are evaluated in order.
+/// For each of the author and the committer, the first matching rule which replaces it is applied.
+pub fn replace_rules(
+    context: &GitContext,
+    rules: &[(ReplaceFilter, ReplaceTarget)],
+    revision: &RevisionSpec,
+    engine: RewriteEngine,
+) -> Result<JournalEntry, ReplaceError> {
+    replace_impl(context, rules, revision, engine, false)
+}
+
+/// Returns the commits which `replace_rules` rewrites without rewriting them.
+pub fn replace_rules_dry_run(
+    context: &GitContext,
+    rules: &[(ReplaceFilter, ReplaceTarget)],
+    revision: &RevisionSpec,
+) -> Result<Vec<ReplacedCommit>, ReplaceError> {
+    plan_replace(context, rules, revision)
+}
+
+/// Same as `replace_rules`, and also replaces the taggers of the annotated tags.
+///
+/// The tagger is tested by the filters as the author and the committer of the tag,
+/// and is replaced with the new committer, or with the new author if the committer is not replaced.
+/// Not supported by `RewriteEngine::FilterBranch`.
+pub fn replace_rules_with_taggers(
+    context: &GitContext,
+    rules: &[(ReplaceFilter, ReplaceTarget)],
+    revision: &RevisionSpec,
+    engine: RewriteEngine,
+) -> Result<JournalEntry, ReplaceError> {
+    replace_impl(context, rules, revision, engine, true)
+}
+
+/// Returns the commits and the tags which `replace_rules` or `replace_rules_with_taggers` rewrites
+/// without rewriting them. The history is walked once for both.
+pub fn replace_rules_dry_run_with_tags(
+    context: &GitContext,
+    rules: &[(ReplaceFilter, ReplaceTarget)],
+    revision: &RevisionSpec,
+    rewrite_taggers: bool,
+) -> Result<(Vec<ReplacedCommit>, Vec<ReplacedTag>), ReplaceError> {
+    let plan = plan_replace_with_tags(context, rules, revision, rewrite_taggers)?;
+    Ok((plan.commits, plan.tags))
+}
+
+/// `.mailmap` in the top-level directory of the working tree
+pub fn default_mailmap_path(context: &GitContext) -> Option<std::path::PathBuf> {
+    Some(get_toplevel(context)?.join(".mailmap"))
+}
+
+/// Mapping which replaces the authors and committers in `revision`
+/// with the canonical identities in `mailmap`.
+///
+/// Each identity in the history which the mailmap changes becomes an exact mapping.
+/// Identities without a name or an email are skipped.
+pub fn mailmap_to_replace_map(
+    context: &GitContext,
+    mailmap: &Mailmap,
+    revision: &RevisionSpec,
+) -> Result<ReplaceMap, OutputError> {
+    let mut identities = std::collections::HashSet::new();
+    let mut entries = vec![];
+    for commit in commits(context, revision)? {
+        let commit = commit?;
+        for identity in [commit.author(), commit.committer()].iter() {
+            if identity.has_none_field() || !identities.insert((*identity).clone()) {
+                continue;
+            }
+            match mailmap.resolve(identity) {
+                Some(canonical) if canonical != **identity && !canonical.has_none_field() => {
+                    entries.push(ReplaceMapEntry::new(
+                        (*identity).clone(),
+                        canonical,
+                        MapScope::Both,
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(ReplaceMap::new(entries))
+}
+
+/// Suggest `.mailmap` entries for the authors and committers in `revision`
+/// which are probably the same person. See `Mailmap::suggest`.
+pub fn suggest_mailmap(
+    context: &GitContext,
+    mailmap: &Mailmap,
+    revision: &RevisionSpec,
+) -> Result<Vec<MailmapEntry>, OutputError> {
+    let identities: Vec<_> = identity_stats(context, revision)?
+        .into_iter()
+        .map(|stats| {
+            let count = stats.author_count() + stats.committer_count();
+            (stats.identity().clone(), count)
+        })
+        .collect();
+    Ok(mailmap.suggest(&identities))
+}
+
+/// Statistics of the authors and committers in `revision` in order of appearance from the newest.
+pub fn identity_stats(
+    context: &GitContext,
+    revision: &RevisionSpec,
+) -> Result<Vec<IdentityStats>, OutputError> {
+    let mut indices = std::collections::HashMap::new();
+    let mut stats: Vec<IdentityStats> = vec![];
+    for commit in commits(context, revision)? {
+        let commit = commit?;
+        let mut identities = vec![commit.author()];
+        if commit.committer() != commit.author() {
+            identities.push(commit.committer());
+        }
+        for identity in identities {
+            let i = *indices.entry(identity.clone()).or_insert_with(|| {
+                stats.push(IdentityStats::new(identity.clone()));
+                stats.len() - 1
+            });
+            stats[i].add(&commit);
+        }
+    }
+    Ok(stats)
+}
+
+/// Authors and committers in `revision` which are probably the same person.
+/// See `IdentityCluster::detect`.
+pub fn find_duplicates(
+    context: &GitContext,
+    revision: &RevisionSpec,
+) -> Result<Vec<IdentityCluster>, OutputError> {
+    Ok(IdentityCluster::detect(&identity_stats(context, revision)?))
+}
+
+/// Rules of `replace_simple`. They can be combined with other filters and passed to `replace_rules`.
+pub fn simple_rules(
+    target: &Author,
+    new_author: &IdentityTemplate,
+) -> Vec<(ReplaceFilter, ReplaceTarget)> {
+    vec![
+        (
+            ReplaceFilter::AuthorAndCommitter {
+                author: target.clone().into(),
+                committer: target.clone().into(),
+            },
+            ReplaceTarget::AuthorAndCommitter {
+                new_author: new_author.clone(),
+                new_committer: new_author.clone(),
+            },
+        ),
+        (
+            ReplaceFilter::AuthorOnly(target.clone().into()),
+            ReplaceTarget::Author {
+                new_author: new_author.clone(),
+            },
+        ),
+        (
+            ReplaceFilter::CommitterOnly(target.clone().into()),
+            ReplaceTarget::Committer {
+                new_committer: new_author.clone(),
+            },
+        ),
+    ]
+}
+
+/// Seconds since the unix epoch as `YYYY-MM-DD hh:mm:ss UTC`
+fn time_to_text(time: i64) -> String {
+    let days = time.div_euclid(86400);
+    let seconds = time.rem_euclid(86400);
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Quote `text` as a single word of the shell.
+/// Every character is literal in single quotes, so only `'` needs to be escaped.
+fn quote_shell(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Serialize a regex as its pattern.
+fn serialize_regex<S: serde::Serializer>(
+    regex: &regex::Regex,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(regex.as_str())
+}
+
+/// `--env-filter` script of `git filter-branch`.
+/// For each of the author and the committer, the first matching rule which replaces it is applied.
+fn rules_to_env_filter(rules: &[(ReplaceFilter, ReplaceTarget)]) -> Result<String, ReplaceError> {
+    // All filters are tested before replacing so that they see the original values.
+    let mut script = String::from("\n");
+    for (i, (filter, _)) in rules.iter().enumerate() {
+        script += &format!(
+            "        matched{i}=; if [ {} ]; then matched{i}=1; fi\n",
+            filter.to_condition_text()?,
+            i = i
+        );
+    }
+
+    let mut author_contents = vec![];
+    let mut committer_contents = vec![];
+    for (i, (_, target)) in rules.iter().enumerate() {
+        let (new_author, new_committer) = target.replaced();
+        if let Some(new_author) = new_author {
+            let target = ReplaceTarget::Author {
+                new_author: new_author.clone(),
+            };
+            author_contents.push((i, target.to_proccessing_content_text()));
+        }
+        if let Some(new_committer) = new_committer {
+            let target = ReplaceTarget::Committer {
+                new_committer: new_committer.clone(),
+            };
+            committer_contents.push((i, target.to_proccessing_content_text()));
+        }
+    }
+    for contents in [author_contents, committer_contents].iter() {
+        for (n, (i, content)) in contents.iter().enumerate() {
+            script += &format!(
+                "        {} [ -n \"$matched{}\" ]; then\n            {}\n",
+                if n == 0 { "if" } else { "elif" },
+                i,
+                content
+            );
+        }
+        if !contents.is_empty() {
+            script += "        fi\n";
+        }
+    }
+    Ok(script)
+}
+
+/// Iterate over the commits of `revision` from the newest.
+pub fn commits(context: &GitContext, revision: &RevisionSpec) -> Result<CommitIter, OutputError> {
+    CommitIter::new(context, revision)
+}
+
+/// get the commits of `revision`
+fn get_commits(context: &GitContext, revision: &RevisionSpec) -> Result<Vec<Commit>, OutputError> {
+    CommitIter::new(context, revision)?.collect()
+}
+
+/// get the commits of `revision` with whether they touch each of `pathspecs`
+fn get_commits_with_pathspecs(
+    context: &GitContext,
+    revision: &RevisionSpec,
+    pathspecs: &[&str],
+) -> Result<Vec<Commit>, OutputError> {
+    let mut commits = get_commits(context, revision)?;
+    for pathspec in pathspecs {
+        // filter-branch runs the env-filter in the top-level directory too.
+        let mut command = context.command();
+        if let Some(toplevel) = get_toplevel(context) {
+            command.arg("-C").arg(toplevel);
+        }
+        let output = command
+            .arg("rev-list")
+            .arg("--full-history")
+            .args(revision.to_args())
+            .arg("--")
+            .arg(pathspec)
+            .output()?;
+        let stdout = output_to_result(output)?;
+        let shas: std::collections::HashSet<_> = stdout.lines().collect();
+        for commit in commits.iter_mut().filter(|c| shas.contains(c.sha())) {
+            commit.add_touched_pathspec(pathspec);
+        }
+    }
+    Ok(commits)
+}
+
+/// new author, new committer and the matched predicates of the commit by the rules
+fn apply_rules(
+    rules: &[(ReplaceFilter, ReplaceTarget)],
+    commit: &Commit,
+) -> (Option<Author>, Option<Author>, Vec<String>) {
+    let mut new_author = None;
+    let mut new_committer = None;
+    let mut matched_predicates = vec![];
+    for (filter, target) in rules.iter().filter(|(filter, _)| filter.matches(commit)) {
+        let (author, committer) = target.replaced();
+        let applied = (new_author.is_none() && author.is_some())
+            || (new_committer.is_none() && committer.is_some());
+        if applied {
+            new_author = new_author.or_else(|| author.map(|t| t.apply(commit.author())));
+            new_committer =
+                new_committer.or_else(|| committer.map(|t| t.apply(commit.committer())));
+            matched_predicates.extend(filter.matched_predicates(commit));
+        }
+    }
+    (new_author, new_committer, matched_predicates)
+}
+
+/// find the commits which match the rules and the new author and committer
+fn plan_replace(
+    context: &GitContext,
+    rules: &[(ReplaceFilter, ReplaceTarget)],
+    revision: &RevisionSpec,
+) -> Result<Vec<ReplacedCommit>, ReplaceError> {
+    Ok(plan_replace_with_tags(context, rules, revision, false)?.commits)
+}
+
+/// commits and tags which `replace` rewrites
+struct ReplacePlan {
+    commits: Vec<ReplacedCommit>,
+    tags: Vec<ReplacedTag>,
+    /// the newest commits which are not rewritten. They and their ancestors are not exported.
+    kept_heads: Vec<String>,
+}
+
+/// find the commits and the tags which are rewritten
+///
+/// A tag which points at a commit in `revision` is rewritten if the SHA of the commit changes,
+/// or if `rewrite_taggers` and its tagger is replaced.
+fn plan_replace_with_tags(
+    context: &GitContext,
+    rules: &[(ReplaceFilter, ReplaceTarget)],
+    revision: &RevisionSpec,
+    rewrite_taggers: bool,
+) -> Result<ReplacePlan, ReplaceError> {
+    let mut pathspecs: Vec<_> = rules
+        .iter()
+        .flat_map(|(filter, _)| filter.pathspecs())
+        .collect();
+    pathspecs.sort_unstable();
+    pathspecs.dedup();
+
+    let commits = get_commits_with_pathspecs(context, revision, &pathspecs)?;
+
+    // A commit is rewritten if it is replaced or one of its parents is rewritten.
+    // The commits are in topological order, so the parents are visited first in reverse.
+    let shas: std::collections::HashSet<_> = commits.iter().map(|c| c.sha().to_string()).collect();
+    let mut rewritten = std::collections::HashSet::new();
+    let mut kept = vec![];
+    let mut kept_parents = std::collections::HashSet::new();
+    let mut replaced_commits = vec![];
+    for commit in commits.into_iter().rev() {
+        let (new_author, new_committer, matched_predicates) = apply_rules(rules, &commit);
+        let parent_rewritten = commit.parents().iter().any(|p| rewritten.contains(p));
+        if new_author.is_some() || new_committer.is_some() || parent_rewritten {
+            rewritten.insert(commit.sha().to_string());
+        } else {
+            // The parents of a kept commit are kept too.
+            kept_parents.extend(commit.parents().iter().cloned());
+            kept.push(commit.sha().to_string());
+        }
+        if new_author.is_some() || new_committer.is_some() {
+            replaced_commits.push(ReplacedCommit::new(
+                commit,
+                new_author,
+                new_committer,
+                matched_predicates,
+            ));
+        }
+    }
+    replaced_commits.reverse();
+    let kept_heads = kept
+        .into_iter()
+        .filter(|sha| !kept_parents.contains(sha))
+        .collect();
+
+    let replaced_tags = get_tags(context)?
+        .into_iter()
+        .filter(|tag| shas.contains(tag.target()))
+        .filter_map(|tag| {
+            let (new_tagger, matched_predicates) = match Commit::from_tag(&tag) {
+                Some(commit) if rewrite_taggers => {
+                    let (new_author, new_committer, matched_predicates) =
+                        apply_rules(rules, &commit);
+                    (new_committer.or(new_author), matched_predicates)
+                }
+                _ => (None, vec![]),
+            };
+            if new_tagger.is_none() && !rewritten.contains(tag.target()) {
+                return None;
+            }
+            Some(ReplacedTag::new(tag, new_tagger, matched_predicates))
+        })
+        .collect();
+    Ok(ReplacePlan {
+        commits: replaced_commits,
+        tags: replaced_tags,
+        kept_heads,
+    })
 }
 
 /// impl replace
-fn replace_impl(condition_arg: &str) -> Result<(), ReplaceError> {
-    let output = std::process::Command::new("git")
+///
+/// The rewritten refs are recorded in the journal so that `replace_undo` can restore them.
+fn replace_impl(
+    context: &GitContext,
+    rules: &[(ReplaceFilter, ReplaceTarget)],
+    revision: &RevisionSpec,
+    engine: RewriteEngine,
+    rewrite_taggers: bool,
+) -> Result<JournalEntry, ReplaceError> {
+    let env_filter = match engine {
+        RewriteEngine::FastExport => None,
+        RewriteEngine::FilterBranch if rewrite_taggers => {
+            return Err(ReplaceError::UnsupportedByFilterBranch(
+                "rewriting taggers".to_string(),
+            ))
+        }
+        RewriteEngine::FilterBranch => Some(rules_to_env_filter(rules)?),
+    };
+
+    let journal_path = ReplaceJournal::path_in(&get_git_common_dir(context)?);
+    let mut journal = ReplaceJournal::load(&journal_path)?;
+    let old_refs = get_refs(context)?;
+
+    let plan = plan_replace_with_tags(context, rules, revision, rewrite_taggers)?;
+    match env_filter {
+        None => rewrite_with_fast_export(context, &plan, revision)?,
+        Some(env_filter) => rewrite_with_filter_branch(context, &env_filter, revision)?,
+    }
+
+    let new_refs = get_refs(context)?;
+    let updates: Vec<_> = old_refs
+        .iter()
+        .filter_map(|(name, old)| match new_refs.get(name) {
+            Some(new) if new != old => Some(RefUpdate::new(name, old, new)),
+            _ => None,
+        })
+        .collect();
+    let invalidated_tags = plan
+        .tags
+        .iter()
+        .map(|replaced| replaced.tag())
+        .filter(|tag| tag.is_signed() && updates.iter().any(|u| u.name() == tag.name()))
+        .map(|tag| tag.name().to_string())
+        .collect();
+    let time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let mut entry = JournalEntry::new(time, revision.to_string(), updates);
+    entry.set_invalidated_tags(invalidated_tags);
+    if !entry.refs().is_empty() {
+        journal.push(entry.clone());
+        journal.save()?;
+    }
+
+    Ok(entry)
+}
+
+/// rewrite `revision` with `git filter-branch`
+///
+/// The tags which point at the rewritten commits are rewritten too, and their signatures become invalid.
+fn rewrite_with_filter_branch(
+    context: &GitContext,
+    condition_arg: &str,
+    revision: &RevisionSpec,
+) -> Result<(), ReplaceError> {
+    let output = context
+        .command()
         .arg("filter-branch")
         .arg("-f")
         .arg("--env-filter")
         .arg(condition_arg)
+        .arg("--tag-name-filter")
+        .arg("cat")
+        .arg("--")
+        .args(revision.to_args())
         .output()?;
     output_to_result(output)?;
+    Ok(())
+}
+
+/// rewrite `revision` with `git fast-export | git fast-import`
+///
+/// Blobs and trees are not exported, so only the commits and the tags are written again.
+/// The commits which are kept are not exported, so their SHAs and signatures are kept.
+/// The PGP signatures of the rewritten tags are removed, and the other signatures become invalid.
+fn rewrite_with_fast_export(
+    context: &GitContext,
+    plan: &ReplacePlan,
+    revision: &RevisionSpec,
+) -> Result<(), ReplaceError> {
+    use std::process::Stdio;
+
+    let commit_replacements = plan.commits.iter().map(|replaced| {
+        (
+            replaced.commit().sha().to_string(),
+            (
+                replaced.new_author().cloned(),
+                replaced.new_committer().cloned(),
+            ),
+        )
+    });
+    // A lightweight tag has the SHA of the commit, but it has no tagger.
+    let tag_replacements = plan.tags.iter().filter_map(|replaced| {
+        let new_tagger = replaced.new_tagger()?;
+        Some((
+            replaced.tag().sha().to_string(),
+            (None, Some(new_tagger.clone())),
+        ))
+    });
+    let replacements: fast_export::Replacements =
+        commit_replacements.chain(tag_replacements).collect();
+    // Checked before fast-import starts so that a broken ident does not leave a partial import.
+    for (new_author, new_committer) in replacements.values() {
+        for author in new_author.iter().chain(new_committer.iter()) {
+            fast_export::check_ident(author)?;
+        }
+    }
+
+    // Only the tags which change are exported so that the other signed tags are kept.
+    // A tag of a kept commit still points at the original commit with `rewrite`.
+    let mut export = context
+        .command()
+        .arg("fast-export")
+        .arg("--no-data")
+        .arg("--show-original-ids")
+        .arg("--reference-excluded-parents")
+        .arg("--reencode=no")
+        .arg("--signed-tags=strip")
+        .arg("--tag-of-filtered-object=rewrite")
+        .args(revision.to_branch_args())
+        .args(plan.tags.iter().map(|replaced| replaced.tag().name()))
+        .args(plan.kept_heads.iter().map(|sha| format!("^{}", sha)))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut import = context
+        .command()
+        .arg("fast-import")
+        .arg("--force")
+        .arg("--quiet")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
 
+    // stdin of fast-import is closed at the end so that it finishes.
+    let result = match (export.stdout.take(), import.stdin.take()) {
+        (Some(stdout), Some(stdin)) => fast_export::rewrite_stream(
+            std::io::BufReader::new(stdout),
+            std::io::BufWriter::new(stdin),
+            &replacements,
+        ),
+        _ => Ok(()),
+    };
+    output_to_result(export.wait_with_output()?)?;
+    output_to_result(import.wait_with_output()?)?;
+    result?;
     Ok(())
 }
+
+/// Parse a date like `git log --since`. e.g. `2020-01-01`, `2 weeks ago`
+///
+/// Returns seconds since the unix epoch. `None` if git cannot parse it.
+pub fn parse_date(context: &GitContext, date: &str) -> Result<Option<i64>, OutputError> {
+    let output = context
+        .command()
+        .arg("rev-parse")
+        .arg(format!("--since={}", date))
+        .output()?;
+    let stdout = output_to_result(output)?;
+    Ok(stdout
+        .trim_end_matches('\n')
+        .strip_prefix("--max-age=")
+        .and_then(|time| time.parse().ok()))
+}
+
+/// get the directory shared by all worktrees. e.g. `.git`
+fn get_git_common_dir(context: &GitContext) -> Result<std::path::PathBuf, OutputError> {
+    let output = context
+        .command()
+        .arg("rev-parse")
+        .arg("--git-common-dir")
+        .output()?;
+    let stdout = output_to_result(output)?;
+    Ok(context.resolve(stdout.trim_end_matches('\n').into()))
+}
+
+/// get the tags
+fn get_tags(context: &GitContext) -> Result<Vec<Tag>, OutputError> {
+    let output = context
+        .command()
+        .arg("for-each-ref")
+        .arg(format!("--format={}", Tag::REF_FORMAT))
+        .arg("refs/tags")
+        .output()?;
+    let stdout = output_to_result(output)?;
+    Ok(Tag::parse_all(&stdout))
+}
+
+/// get the SHA of HEAD if it is detached
+fn get_detached_head(context: &GitContext) -> Result<Option<String>, OutputError> {
+    let output = context
+        .command()
+        .arg("symbolic-ref")
+        .arg("-q")
+        .arg("HEAD")
+        .output()?;
+    if output.status.success() {
+        return Ok(None);
+    }
+    let output = context
+        .command()
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("HEAD")
+        .output()?;
+    Ok(Some(
+        output_to_result(output)?.trim_end_matches('\n').to_string(),
+    ))
+}
+
+/// get the SHAs of branches and tags, and of HEAD if it is detached
+fn get_refs(
+    context: &GitContext,
+) -> Result<std::collections::BTreeMap<String, String>, OutputError> {
+    let output = context
+        .command()
+        .arg("for-each-ref")
+        .arg("--format=%(objectname) %(refname)")
+        .arg("refs/heads")
+        .arg("refs/tags")
+        .output()?;
+    let stdout = output_to_result(output)?;
+    let mut refs: std::collections::BTreeMap<_, _> = stdout
+        .lines()
+        .filter_map(|line| {
+            let (sha, name) = line.split_once(' ')?;
+            Some((name.to_string(), sha.to_string()))
+        })
+        .collect();
+    // fast-import moves a detached HEAD too, so it is restored by `replace_undo`.
+    if let Some(sha) = get_detached_head(context)? {
+        refs.insert("HEAD".to_string(), sha);
+    }
+    Ok(refs)
+}
+
+/// Get the history of `replace`, from the oldest.
+pub fn replace_history(context: &GitContext) -> Result<Vec<JournalEntry>, JournalError> {
+    let journal = ReplaceJournal::load(ReplaceJournal::path_in(&get_git_common_dir(context)?))?;
+    Ok(journal.entries().to_vec())
+}
+
+/// Restore the refs rewritten by the last `replace` which is not undone yet.
+///
+/// Fails without changing anything if one of the refs was changed after the rewrite.
+pub fn replace_undo(context: &GitContext) -> Result<JournalEntry, JournalError> {
+    let mut journal = ReplaceJournal::load(ReplaceJournal::path_in(&get_git_common_dir(context)?))?;
+    let entry = journal
+        .last_undoable()
+        .cloned()
+        .ok_or(JournalError::NothingToUndo)?;
+
+    // `update <ref> <new> <old>` fails if the ref is not `<old>`, and no ref is updated.
+    let mut child = context
+        .command()
+        .arg("update-ref")
+        .arg("--no-deref")
+        .arg("--stdin")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    if let Some(stdin) = child.stdin.as_mut() {
+        use std::io::Write;
+        for update in entry.refs() {
+            writeln!(
+                stdin,
+                "update {} {} {}",
+                update.name(),
+                update.old_sha(),
+                update.new_sha()
+            )?;
+        }
+    }
+    let output = child.wait_with_output()?;
+    output_to_result(output)?;
+
+    let entry = journal.mark_last_undone().unwrap_or(entry);
+    journal.save()?;
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    /// Names which the shell would expand, split or unquote if they were not quoted well.
+    fn adversarial_names() -> Vec<String> {
+        const PIECES: [&str; 20] = [
+            "a",
+            "Foo Bar",
+            "'",
+            "''",
+            "\"",
+            "$",
+            "$HOME",
+            "${HOME}",
+            "$(echo x)",
+            "`echo x`",
+            "\\",
+            "\\'",
+            " ",
+            "\t",
+            "\n",
+            "!",
+            "-n",
+            "*",
+            "{}",
+            "日本語 é",
+        ];
+        let mut names: Vec<String> = PIECES.iter().map(|piece| piece.to_string()).collect();
+        // xorshift: the names are the same for every run.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..100 {
+            let length = 1 + next() % 6;
+            names.push(
+                (0..length)
+                    .map(|_| PIECES[(next() % PIECES.len() as u64) as usize])
+                    .collect(),
+            );
+        }
+        names
+    }
+
+    /// A template cannot have a newline, but the old values can.
+    fn without_newlines(text: &str) -> String {
+        text.replace('\n', " ")
+    }
+
+    fn escape_template(text: &str) -> String {
+        text.replace('{', "{{").replace('}', "}}")
+    }
+
+    /// Run the env-filter script as `git filter-branch` does and return the fields after it.
+    fn run_env_filter(script: &str, author: &Author, committer: &Author) -> [String; 4] {
+        let field = |field: &Option<String>| field.clone().unwrap_or_default();
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(format!(
+                r#"{}
+printf '%s\0' "$GIT_AUTHOR_NAME" "$GIT_AUTHOR_EMAIL" "$GIT_COMMITTER_NAME" "$GIT_COMMITTER_EMAIL""#,
+                script
+            ))
+            .env("GIT_AUTHOR_NAME", field(author.name()))
+            .env("GIT_AUTHOR_EMAIL", field(author.email()))
+            .env("GIT_COMMITTER_NAME", field(committer.name()))
+            .env("GIT_COMMITTER_EMAIL", field(committer.email()))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let fields: Vec<String> = stdout.split('\0').map(str::to_string).collect();
+        assert_eq!(fields.len(), 5, "{:?}", stdout);
+        [
+            fields[0].clone(),
+            fields[1].clone(),
+            fields[2].clone(),
+            fields[3].clone(),
+        ]
+    }
+
+    /// A repository with `count` empty commits by `Foo <foo@example.com>` in the temporary directory.
+    fn repository(name: &str, count: usize) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("git-author-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        let context = GitContext::new(path.clone())
+            .with_env("GIT_AUTHOR_NAME", "Foo")
+            .with_env("GIT_AUTHOR_EMAIL", "foo@example.com")
+            .with_env("GIT_COMMITTER_NAME", "Foo")
+            .with_env("GIT_COMMITTER_EMAIL", "foo@example.com");
+        let git = |args: &[&str]| {
+            output_to_result(context.command().args(args).output().unwrap()).unwrap();
+        };
+        git(&["init", "-q"]);
+        for i in 0..count {
+            git(&[
+                "commit",
+                "-q",
+                "--allow-empty",
+                "-m",
+                &format!("commit {}", i),
+            ]);
+        }
+        path
+    }
+
+    fn head(context: &GitContext) -> String {
+        let output = context.command().args(["rev-parse", "HEAD"]).output();
+        output_to_result(output.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn undo_restores_the_refs() {
+        let path = repository("undo", 2);
+        let context = GitContext::new(path.clone());
+        let old_head = head(&context);
+        let old = Author::from_raw("Foo", "foo@example.com");
+        let new = Author::from_raw("Bar", "bar@example.com");
+
+        let entry = replace_simple(
+            &context,
+            old.clone(),
+            new.clone(),
+            &RevisionSpec::Head,
+            RewriteEngine::FastExport,
+        )
+        .unwrap();
+        let new_head = head(&context);
+        assert_ne!(new_head, old_head);
+        assert_eq!(entry.refs().len(), 1);
+        assert_eq!(entry.refs()[0].old_sha(), old_head.trim_end());
+        assert_eq!(entry.refs()[0].new_sha(), new_head.trim_end());
+        assert_eq!(replace_history(&context).unwrap(), vec![entry.clone()]);
+
+        let undone = replace_undo(&context).unwrap();
+        assert_eq!(undone.refs(), entry.refs());
+        assert!(undone.is_undone());
+        assert_eq!(head(&context), old_head);
+        assert!(matches!(
+            replace_undo(&context),
+            Err(JournalError::NothingToUndo)
+        ));
+
+        // A ref which was changed after the rewrite is not overwritten.
+        replace_simple(
+            &context,
+            old,
+            new,
+            &RevisionSpec::Head,
+            RewriteEngine::FastExport,
+        )
+        .unwrap();
+        let commit_output = context
+            .command()
+            .args(["-c", "user.name=Baz", "-c", "user.email=baz@example.com"])
+            .args(["commit", "-q", "--allow-empty", "-m", "later"])
+            .output();
+        output_to_result(commit_output.unwrap()).unwrap();
+        let later_head = head(&context);
+        assert!(replace_undo(&context).is_err());
+        assert_eq!(head(&context), later_head);
+        assert!(!replace_history(&context).unwrap()[1].is_undone());
+
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn tags_from_git() {
+        let path = repository("tags", 1);
+        let context = GitContext::new(path.clone())
+            .with_env("GIT_COMMITTER_NAME", "Tagger")
+            .with_env("GIT_COMMITTER_EMAIL", "tagger@example.com");
+        let git = |args: &[&str]| {
+            output_to_result(context.command().args(args).output().unwrap()).unwrap();
+        };
+        git(&["tag", "-a", "v1", "-m", "release 1\n\nline 1\nline 2"]);
+        git(&["tag", "light"]);
+        let commit = head(&context);
+
+        let tags = get_tags(&context).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[0].name(), "refs/tags/light");
+        assert_eq!(tags[0].target(), commit.trim_end());
+        assert_eq!(tags[0].tagger(), None);
+        assert_eq!(tags[1].name(), "refs/tags/v1");
+        assert_ne!(tags[1].sha(), tags[1].target());
+        assert_eq!(tags[1].target(), commit.trim_end());
+        assert_eq!(
+            tags[1].tagger(),
+            Some(&Author::from_raw("Tagger", "tagger@example.com"))
+        );
+        assert_eq!(tags[1].subject(), "release 1");
+        assert_eq!(tags[1].message(), "release 1\n\nline 1\nline 2\n");
+    }
+
+    #[test]
+    fn identity_stats_from_git() {
+        let path = repository("stats", 2);
+        let context = GitContext::new(path.clone())
+            .with_env("GIT_AUTHOR_NAME", "Bar")
+            .with_env("GIT_AUTHOR_EMAIL", "bar@example.com")
+            .with_env("GIT_COMMITTER_NAME", "Foo")
+            .with_env("GIT_COMMITTER_EMAIL", "foo@example.com");
+        let output = context
+            .command()
+            .args(["commit", "-q", "--allow-empty", "-m", "by bar"])
+            .output();
+        output_to_result(output.unwrap()).unwrap();
+
+        let stats = identity_stats(&context, &RevisionSpec::Head).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+        let counts: Vec<_> = stats
+            .iter()
+            .map(|s| {
+                (
+                    s.identity().to_string(),
+                    s.commit_count(),
+                    s.author_count(),
+                    s.committer_count(),
+                    s.mismatch_count(),
+                )
+            })
+            .collect();
+        // In order of appearance from the newest.
+        assert_eq!(
+            counts,
+            vec![
+                ("Bar <bar@example.com>".to_string(), 1, 1, 0, 1),
+                ("Foo <foo@example.com>".to_string(), 3, 2, 3, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn include_rules_are_idempotent() {
+        let base = std::env::temp_dir().join(format!("git-author-{}-rules", std::process::id()));
+        let global = base.join("gitconfig");
+        std::fs::create_dir_all(&base).unwrap();
+        let hand_written = "[includeIf \"gitdir:~/oss/\"]\n\tpath = ~/.gitconfig-oss\n";
+        std::fs::write(&global, hand_written).unwrap();
+        // `IncludeRule::default_dir` is read from the environment of the process.
+        std::env::set_var("XDG_CONFIG_HOME", base.join("config"));
+        let context = GitContext::new(&base).with_env("GIT_CONFIG_GLOBAL", &global);
+        let work = Profile::new("work", Author::from_raw("Foo", "foo@work.example.com"));
+        let other = Profile::new("other", Author::from_raw("Bar", "bar@example.com"));
+
+        let rule = add_include_rule(&context, "~/work/", &work).unwrap();
+        let text = std::fs::read_to_string(&global).unwrap();
+        assert!(text.starts_with(hand_written));
+        assert_eq!(include_rules(&context).unwrap(), vec![rule.clone()]);
+        assert_eq!(
+            get_author(
+                &context,
+                Some(&ConfigFileLocation::File(rule.path().to_path_buf())),
+                Role::User
+            )
+            .unwrap(),
+            *work.author()
+        );
+
+        // Adding the same rule again changes nothing.
+        assert_eq!(add_include_rule(&context, "~/work/", &work).unwrap(), rule);
+        assert_eq!(std::fs::read_to_string(&global).unwrap(), text);
+
+        // Another profile for the same directory replaces the rule and its unused file.
+        let other_rule = add_include_rule(&context, "~/work/", &other).unwrap();
+        assert_eq!(include_rules(&context).unwrap(), vec![other_rule.clone()]);
+        assert!(!rule.path().exists());
+
+        assert_eq!(
+            remove_include_rule(&context, "~/work/").unwrap(),
+            vec![other_rule.clone()]
+        );
+        assert!(!other_rule.path().exists());
+        assert!(matches!(
+            remove_include_rule(&context, "~/work/"),
+            Err(RuleError::NotFound(_))
+        ));
+        let text = std::fs::read_to_string(&global).unwrap();
+        std::fs::remove_dir_all(&base).unwrap();
+        assert_eq!(text, hand_written);
+    }
+
+    #[test]
+    fn quote_shell_round_trips() {
+        for name in adversarial_names() {
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf '%s' {}", quote_shell(&name)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn env_filter_replaces_exact_names() {
+        let names = adversarial_names();
+        for (i, name) in names.iter().enumerate() {
+            let email = &names[(i + 1) % names.len()];
+            let new_name = &without_newlines(&names[(i + 2) % names.len()]);
+            let new_email = &without_newlines(&names[(i + 3) % names.len()]);
+            let old = Author::from_raw(name, email);
+            let other = Author::from_raw(&format!("{}x", name), email);
+            let template = IdentityTemplate::new(
+                Some(&escape_template(new_name)),
+                Some(&escape_template(new_email)),
+            )
+            .unwrap();
+            let script = rules_to_env_filter(&simple_rules(&old, &template)).unwrap();
+
+            let new = [new_name, new_email, new_name, new_email].map(|s| s.to_string());
+            assert_eq!(run_env_filter(&script, &old, &old), new, "{:?}", old);
+
+            let replaced = run_env_filter(&script, &old, &other);
+            assert_eq!(replaced[..2], new[..2], "{:?}", old);
+            assert_eq!(replaced[2], format!("{}x", name), "{:?}", old);
+            assert_eq!(&replaced[3], email, "{:?}", old);
+        }
+    }
+
+    #[test]
+    fn env_filter_expands_old_values() {
+        for name in adversarial_names() {
+            let old = Author::from_raw(&name, &format!("{}@{}", name, name));
+            // The fields are swapped, so each of them sees the old value of the other.
+            let text = without_newlines(&name);
+            let template = IdentityTemplate::new(
+                Some("{email}"),
+                Some(&format!("{{localpart}}{}{{name}}", escape_template(&text))),
+            )
+            .unwrap();
+            let script = rules_to_env_filter(&simple_rules(&old, &template)).unwrap();
+            let expected_email = format!("{}{}{}", name, text, name);
+            assert_eq!(
+                run_env_filter(&script, &old, &old),
+                [
+                    format!("{}@{}", name, name),
+                    expected_email.clone(),
+                    format!("{}@{}", name, name),
+                    expected_email,
+                ],
+                "{:?}",
+                old
+            );
+        }
+    }
+}
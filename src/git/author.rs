@@ -1,12 +1,32 @@
+use super::Ident;
+use crate::error::AuthorParseError;
 use addr::email;
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "AuthorFields")]
 pub struct Author {
     name: Option<String>,
     email: Option<String>,
 }
 
+/// `Author` before it is validated by `Author::new`
+#[derive(Deserialize)]
+struct AuthorFields {
+    name: Option<String>,
+    email: Option<String>,
+}
+
+impl std::convert::TryFrom<AuthorFields> for Author {
+    type Error = addr::Error;
+
+    fn try_from(fields: AuthorFields) -> Result<Self, Self::Error> {
+        Author::new(fields.name, fields.email)
+    }
+}
+
 impl Author {
     pub fn new<S1, S2>(name: Option<S1>, email: Option<S2>) -> Result<Self, addr::Error>
     where
@@ -30,6 +50,22 @@ impl Author {
         Ok(Author { name, email })
     }
 
+    /// Create from name and email recorded in a commit without validation.
+    /// Empty values are `None`.
+    pub(crate) fn from_raw(name: &str, email: &str) -> Self {
+        let to_option = |s: &str| {
+            if s.is_empty() {
+                None
+            } else {
+                Some(s.to_string())
+            }
+        };
+        Author {
+            name: to_option(name),
+            email: to_option(email),
+        }
+    }
+
     pub fn name(&self) -> &Option<String> {
         &self.name
     }
@@ -53,3 +89,93 @@ impl fmt::Display for Author {
         }
     }
 }
+
+/// `(name, email, rest)` of `Name <email> rest` with the spaces around them trimmed.
+pub(crate) fn split_ident(text: &str) -> Option<(&str, &str, &str)> {
+    let start = text.find('<')?;
+    let end = start + text[start..].find('>')?;
+    Some((
+        text[..start].trim(),
+        text[start + 1..end].trim(),
+        text[end + 1..].trim(),
+    ))
+}
+
+impl FromStr for Author {
+    type Err = AuthorParseError;
+
+    /// Parse `Name <email>`, `<email>` or a git ident like `Name <email> 1700000000 +0900`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (name, email, date) =
+            split_ident(text).ok_or_else(|| AuthorParseError::InvalidFormat(text.to_string()))?;
+        if !date.is_empty() {
+            // Only the date is checked. The name and the email are validated below.
+            text.parse::<Ident>()?;
+        }
+        Ok(Author::new(
+            Some(name),
+            Some(email).filter(|email| !email.is_empty()),
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_name_and_email() {
+        let author: Author = "Foo Bar <foo@example.com>".parse().unwrap();
+        assert_eq!(author.name().as_deref(), Some("Foo Bar"));
+        assert_eq!(author.email().as_deref(), Some("foo@example.com"));
+        assert_eq!(author.to_string(), "Foo Bar <foo@example.com>");
+    }
+
+    #[test]
+    fn parse_email_only() {
+        let author: Author = "<foo@example.com>".parse().unwrap();
+        assert_eq!(author.name(), &None);
+        assert_eq!(author.email().as_deref(), Some("foo@example.com"));
+    }
+
+    #[test]
+    fn parse_name_without_email() {
+        let author: Author = "Foo <>".parse().unwrap();
+        assert_eq!(author.name().as_deref(), Some("Foo"));
+        assert_eq!(author.email(), &None);
+    }
+
+    #[test]
+    fn parse_ident_line() {
+        let author: Author = "Foo <foo@example.com> 1700000000 +0900".parse().unwrap();
+        assert_eq!(author.to_string(), "Foo <foo@example.com>");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            "Foo".parse::<Author>(),
+            Err(AuthorParseError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            "Foo <foo@example.com".parse::<Author>(),
+            Err(AuthorParseError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            "Foo <foo@example.com> yesterday".parse::<Author>(),
+            Err(AuthorParseError::InvalidDate(_))
+        ));
+        assert!(matches!(
+            "Foo <not an email>".parse::<Author>(),
+            Err(AuthorParseError::Addr(_))
+        ));
+    }
+
+    #[test]
+    fn deserialize_validates() {
+        let author: Author =
+            serde_json::from_str(r#"{"name": " Foo ", "email": "foo@example.com"}"#).unwrap();
+        assert_eq!(author.name().as_deref(), Some("Foo"));
+        assert!(serde_json::from_str::<Author>(r#"{"name": "Foo", "email": "nope"}"#).is_err());
+    }
+}
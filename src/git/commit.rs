@@ -0,0 +1,147 @@
+use super::{Author, Tag};
+use serde::Serialize;
+
+/// Author and committer of a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Commit {
+    sha: String,
+    subject: String,
+    author: Author,
+    committer: Author,
+    author_time: i64,
+    committer_time: i64,
+    message: String,
+    parents: Vec<String>,
+    #[serde(skip)]
+    touched_pathspecs: Vec<String>,
+}
+
+impl Commit {
+    /// `--format` of `git log -z` for `CommitIter`
+    pub(crate) const LOG_FORMAT: &'static str =
+        "%H%x00%an%x00%ae%x00%cn%x00%ce%x00%s%x00%at%x00%ct%x00%P%x00%B";
+    pub(crate) const FIELD_COUNT: usize = 10;
+
+    /// Create from the fields of `LOG_FORMAT`.
+    pub(crate) fn from_fields(f: &[String]) -> Self {
+        Commit {
+            sha: f[0].to_string(),
+            author: Author::from_raw(&f[1], &f[2]),
+            committer: Author::from_raw(&f[3], &f[4]),
+            subject: f[5].to_string(),
+            author_time: f[6].parse().unwrap_or_default(),
+            committer_time: f[7].parse().unwrap_or_default(),
+            parents: f[8].split_whitespace().map(|p| p.to_string()).collect(),
+            message: f[9].to_string(),
+            touched_pathspecs: vec![],
+        }
+    }
+
+    /// The tagger of an annotated tag as the author and the committer so that filters can test it.
+    /// `None` if the tag is lightweight.
+    pub(crate) fn from_tag(tag: &Tag) -> Option<Self> {
+        let tagger = tag.tagger()?;
+        Some(Commit {
+            sha: tag.sha().to_string(),
+            subject: tag.subject().to_string(),
+            author: tagger.clone(),
+            committer: tagger.clone(),
+            author_time: tag.tagger_time(),
+            committer_time: tag.tagger_time(),
+            message: tag.message().to_string(),
+            parents: vec![],
+            touched_pathspecs: vec![],
+        })
+    }
+
+    pub fn sha(&self) -> &str {
+        &self.sha
+    }
+
+    /// The first line of the commit message.
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    pub fn author(&self) -> &Author {
+        &self.author
+    }
+
+    pub fn committer(&self) -> &Author {
+        &self.committer
+    }
+
+    /// Author date in seconds since the unix epoch.
+    pub fn author_time(&self) -> i64 {
+        self.author_time
+    }
+
+    /// Committer date in seconds since the unix epoch.
+    pub fn committer_time(&self) -> i64 {
+        self.committer_time
+    }
+
+    /// The whole commit message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// SHAs of the parent commits.
+    pub fn parents(&self) -> &[String] {
+        &self.parents
+    }
+
+    /// Whether the commit changes a file in the pathspec.
+    /// Only the pathspecs which are passed when the commit is got are known.
+    pub fn touches(&self, pathspec: &str) -> bool {
+        self.touched_pathspecs.iter().any(|p| p == pathspec)
+    }
+
+    pub(crate) fn add_touched_pathspec(&mut self, pathspec: &str) {
+        self.touched_pathspecs.push(pathspec.to_string());
+    }
+}
+
+/// A commit which is rewritten by `replace`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReplacedCommit {
+    commit: Commit,
+    new_author: Option<Author>,
+    new_committer: Option<Author>,
+    matched_predicates: Vec<String>,
+}
+
+impl ReplacedCommit {
+    pub fn new(
+        commit: Commit,
+        new_author: Option<Author>,
+        new_committer: Option<Author>,
+        matched_predicates: Vec<String>,
+    ) -> Self {
+        ReplacedCommit {
+            commit,
+            new_author,
+            new_committer,
+            matched_predicates,
+        }
+    }
+
+    pub fn commit(&self) -> &Commit {
+        &self.commit
+    }
+
+    /// `None` if the author is not replaced.
+    pub fn new_author(&self) -> Option<&Author> {
+        self.new_author.as_ref()
+    }
+
+    /// `None` if the committer is not replaced.
+    pub fn new_committer(&self) -> Option<&Author> {
+        self.new_committer.as_ref()
+    }
+
+    /// The predicates of the filters which matched the commit. e.g. `author name `foo``
+    pub fn matched_predicates(&self) -> &[String] {
+        &self.matched_predicates
+    }
+}
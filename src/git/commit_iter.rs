@@ -0,0 +1,205 @@
+use super::{output_to_result, Commit, GitContext, RevisionSpec};
+use crate::error::OutputError;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, ChildStdout, Output, Stdio};
+use std::thread::JoinHandle;
+
+/// Commits read from `git log` one by one, from the newest in topological order.
+///
+/// Large histories are not loaded into memory at once.
+/// `git log` is stopped and waited for when the iterator is dropped before the end.
+#[derive(Debug)]
+pub struct CommitIter {
+    child: Option<Child>,
+    reader: Option<BufReader<ChildStdout>>,
+    /// stderr of `git log`, which is read while the commits are read so that the pipe never fills up
+    stderr: Option<JoinHandle<Vec<u8>>>,
+}
+
+impl CommitIter {
+    /// Start `git log` for `revision`.
+    pub fn new(context: &GitContext, revision: &RevisionSpec) -> Result<Self, OutputError> {
+        let mut child = context
+            .command()
+            .arg("log")
+            .arg("-z")
+            .arg("--topo-order")
+            .arg(format!("--format={}", Commit::LOG_FORMAT))
+            .args(revision.to_args())
+            .arg("--")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let reader = child.stdout.take().map(BufReader::new);
+        let stderr = child.stderr.take().map(|mut stderr| {
+            std::thread::spawn(move || {
+                let mut buffer = vec![];
+                let _ = stderr.read_to_end(&mut buffer);
+                buffer
+            })
+        });
+        Ok(CommitIter {
+            child: Some(child),
+            reader,
+            stderr,
+        })
+    }
+
+    /// A field which ends with `\0`, or the last one. `None` at the end.
+    fn read_field(&mut self) -> Result<Option<String>, OutputError> {
+        let reader = match &mut self.reader {
+            Some(reader) => reader,
+            None => return Ok(None),
+        };
+        let mut field = vec![];
+        if reader.read_until(b'\0', &mut field)? == 0 {
+            return Ok(None);
+        }
+        if field.last() == Some(&b'\0') {
+            field.pop();
+        }
+        Ok(Some(String::from_utf8(field)?))
+    }
+
+    fn take_stderr(&mut self) -> Vec<u8> {
+        self.stderr
+            .take()
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default()
+    }
+
+    /// Wait for `git log` and return its error if it failed.
+    fn finish(&mut self) -> Result<(), OutputError> {
+        self.reader = None;
+        match self.child.take() {
+            Some(mut child) => {
+                let status = child.wait()?;
+                let output = Output {
+                    status,
+                    stdout: vec![],
+                    stderr: self.take_stderr(),
+                };
+                output_to_result(output).map(|_| ())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Kill `git log` if it is running and wait for it so that it does not remain as a zombie.
+    fn stop(&mut self) {
+        self.reader = None;
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        self.take_stderr();
+    }
+
+    fn read_commit(&mut self) -> Result<Option<Commit>, OutputError> {
+        let mut fields = Vec::with_capacity(Commit::FIELD_COUNT);
+        while fields.len() < Commit::FIELD_COUNT {
+            match self.read_field()? {
+                Some(field) => fields.push(field),
+                None => {
+                    self.finish()?;
+                    return Ok(None);
+                }
+            }
+        }
+        Ok(Some(Commit::from_fields(&fields)))
+    }
+}
+
+impl Iterator for CommitIter {
+    type Item = Result<Commit, OutputError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_commit() {
+            Ok(commit) => commit.map(Ok),
+            Err(e) => {
+                // Stop `git log` so that the iteration ends after the error.
+                self.stop();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Drop for CommitIter {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    /// A repository with `count` empty commits in the temporary directory.
+    fn repository(name: &str, count: usize) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("git-author-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&path);
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(&path)
+                .args(["-c", "user.name=Foo", "-c", "user.email=foo@example.com"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        std::fs::create_dir_all(&path).unwrap();
+        git(&["init", "-q"]);
+        for i in 0..count {
+            git(&[
+                "commit",
+                "-q",
+                "--allow-empty",
+                "-m",
+                &format!("commit {}", i),
+            ]);
+        }
+        path
+    }
+
+    #[test]
+    fn read_and_drop_early() {
+        let path = repository("commit-iter", 3);
+        let context = GitContext::new(path.clone());
+
+        let commits: Vec<_> = CommitIter::new(&context, &RevisionSpec::Head)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let subjects: Vec<_> = commits.iter().map(|c| c.subject()).collect();
+        assert_eq!(subjects, ["commit 2", "commit 1", "commit 0"]);
+        assert_eq!(
+            commits[0].author(),
+            &crate::git::Author::from_raw("Foo", "foo@example.com")
+        );
+
+        let mut iter = CommitIter::new(&context, &RevisionSpec::Head).unwrap();
+        assert!(iter.next().unwrap().is_ok());
+        drop(iter);
+
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn report_error_of_git_log() {
+        let path = repository("commit-iter-error", 1);
+        let context = GitContext::new(path.clone());
+        let result: Result<Vec<_>, _> =
+            CommitIter::new(&context, &RevisionSpec::Range("no-such-branch".to_string()))
+                .unwrap()
+                .collect();
+        std::fs::remove_dir_all(&path).unwrap();
+        match result {
+            Err(OutputError::CommandExecute(error)) => assert!(error.0.contains("no-such-branch")),
+            result => panic!("{:?}", result),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Where a config value is defined. The `origin` of `git config --show-origin`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigOrigin {
+    /// `file:<path>`. `line` is the line number of the value if it could be found.
+    File { path: PathBuf, line: Option<usize> },
+    /// `command line:`. e.g. `git -c user.name=foo` or `GIT_CONFIG_COUNT`
+    CommandLine,
+    /// `blob:`, `standard input:` and so on.
+    Other(String),
+}
+
+impl std::fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::File {
+                path,
+                line: Some(line),
+            } => write!(f, "file:{}:{}", path.display(), line),
+            Self::File { path, line: None } => write!(f, "file:{}", path.display()),
+            Self::CommandLine => write!(f, "command line"),
+            Self::Other(origin) => write!(f, "{}", origin),
+        }
+    }
+}
+
+/// A value of `git config --show-origin --show-scope`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigEntry {
+    scope: String,
+    origin: ConfigOrigin,
+    value: String,
+}
+
+impl ConfigEntry {
+    pub fn new<S1, S2>(scope: S1, origin: ConfigOrigin, value: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        ConfigEntry {
+            scope: scope.into(),
+            origin,
+            value: value.into(),
+        }
+    }
+
+    /// Parse output of `git config -z --show-origin --show-scope --get-all <key>`.
+    ///
+    /// Relative paths in the output are relative to `base_dir`.
+    pub(crate) fn parse_all(output: &str, key: &str, base_dir: Option<&Path>) -> Vec<Self> {
+        let fields: Vec<_> = output.split('\0').collect();
+        let mut entries: Vec<ConfigEntry> = vec![];
+        for chunk in fields.chunks_exact(3) {
+            let (scope, origin, value) = (chunk[0], chunk[1], chunk[2]);
+            let origin = if let Some(path) = origin.strip_prefix("file:") {
+                let path = match base_dir {
+                    Some(dir) if Path::new(path).is_relative() => dir.join(path),
+                    _ => PathBuf::from(path),
+                };
+                // The n-th value in the file is the n-th definition of the key.
+                let nth = entries
+                    .iter()
+                    .filter(
+                        |e| matches!(&e.origin, ConfigOrigin::File { path: p, .. } if *p == path),
+                    )
+                    .count();
+                let line = find_line(&path, key, nth);
+                ConfigOrigin::File { path, line }
+            } else if origin == "command line:" {
+                ConfigOrigin::CommandLine
+            } else {
+                ConfigOrigin::Other(origin.trim_end_matches(':').to_string())
+            };
+            entries.push(ConfigEntry::new(scope, origin, value));
+        }
+        entries
+    }
+
+    /// `system`, `global`, `local`, `worktree` or `command`
+    pub fn scope(&self) -> &str {
+        &self.scope
+    }
+
+    pub fn origin(&self) -> &ConfigOrigin {
+        &self.origin
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+/// Find the line number (1-based) of the `nth` definition of `key` (e.g. `user.name`) in the file.
+fn find_line(path: &Path, key: &str, nth: usize) -> Option<usize> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let (section, name) = key.split_at(key.rfind('.')?);
+    let name = &name[1..];
+
+    let mut current_section = String::new();
+    let mut count = 0;
+    for (i, line) in text.lines().enumerate() {
+        let mut line = line.trim_start();
+        if line.starts_with('[') {
+            let end = line.find(']')?;
+            current_section = line[1..end].trim().to_string();
+            line = line[end + 1..].trim_start();
+        }
+
+        let variable = line
+            .split(|c: char| c == '=' || c.is_whitespace())
+            .next()
+            .unwrap_or_default();
+        if !variable.is_empty()
+            && current_section.eq_ignore_ascii_case(section)
+            && variable.eq_ignore_ascii_case(name)
+        {
+            if count == nth {
+                return Some(i + 1);
+            }
+            count += 1;
+        }
+    }
+    None
+}
+
+/// All user.name and user.email values.
+/// The last value of each list is the effective value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthorConfigEntries {
+    name: Vec<ConfigEntry>,
+    email: Vec<ConfigEntry>,
+}
+
+impl AuthorConfigEntries {
+    pub fn new(name: Vec<ConfigEntry>, email: Vec<ConfigEntry>) -> Self {
+        AuthorConfigEntries { name, email }
+    }
+
+    /// user.name values in the order git reads them.
+    pub fn name(&self) -> &[ConfigEntry] {
+        &self.name
+    }
+
+    /// user.email values in the order git reads them.
+    pub fn email(&self) -> &[ConfigEntry] {
+        &self.email
+    }
+
+    pub fn effective_name(&self) -> Option<&ConfigEntry> {
+        self.name.last()
+    }
+
+    pub fn effective_email(&self) -> Option<&ConfigEntry> {
+        self.email.last()
+    }
+}
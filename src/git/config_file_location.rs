@@ -1,10 +1,21 @@
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
 /// Config file location
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ConfigFileLocation {
     /// use global config file
     Global,
     /// use repository config file
     Local,
+    /// use system config file
+    System,
+    /// use per-worktree config file. `extensions.worktreeConfig` must be enabled.
+    Worktree,
+    /// use given config file
+    File(PathBuf),
 }
 
 impl std::fmt::Display for ConfigFileLocation {
@@ -13,14 +24,21 @@ impl std::fmt::Display for ConfigFileLocation {
         match &self {
             Global => write!(f, "global"),
             Local => write!(f, "local"),
+            System => write!(f, "system"),
+            Worktree => write!(f, "worktree"),
+            File(path) => write!(f, "file({})", path.display()),
         }
     }
 }
 
 impl ConfigFileLocation {
-    pub const VARIANTRS: [Self; 2] = [Self::Global, Self::Local];
+    /// Locations which can be specified without a value.
+    pub const VARIANTRS: [Self; 4] = [Self::Global, Self::Local, Self::System, Self::Worktree];
 
-    pub fn to_arg(self) -> String {
-        format!("--{}", self)
+    pub fn to_args(&self) -> Vec<OsString> {
+        match self {
+            Self::File(path) => vec!["--file".into(), path.into()],
+            _ => vec![format!("--{}", self).into()],
+        }
     }
 }
@@ -0,0 +1,44 @@
+use super::{Author, ConfigEntry};
+use serde::Serialize;
+
+/// Where a name or an email of the identity comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentitySource {
+    /// Environment variable. e.g. `GIT_AUTHOR_NAME`, `EMAIL`
+    Environment(String),
+    /// Config value. e.g. `author.name`, `user.email`
+    Config { key: String, entry: ConfigEntry },
+    /// Generated by git from the system user name or the host name.
+    System,
+}
+
+impl std::fmt::Display for IdentitySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::Environment(variable) => write!(f, "environment variable {}", variable),
+            Self::Config { key, entry } => {
+                write!(f, "{} ({} {})", key, entry.scope(), entry.origin())
+            }
+            Self::System => write!(f, "generated from the system user name and host name"),
+        }
+    }
+}
+
+/// Sources of the each fields of `EffectiveIdentity`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IdentitySources {
+    pub author_name: IdentitySource,
+    pub author_email: IdentitySource,
+    pub committer_name: IdentitySource,
+    pub committer_email: IdentitySource,
+}
+
+/// Author and committer which git uses for a new commit.
+/// The same as `git var GIT_AUTHOR_IDENT` and `git var GIT_COMMITTER_IDENT`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EffectiveIdentity {
+    pub author: Author,
+    pub committer: Author,
+    pub sources: IdentitySources,
+}
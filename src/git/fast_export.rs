@@ -0,0 +1,198 @@
+use super::Author;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+
+/// New author and committer of the commits, keyed by the original SHA.
+/// `None` if the author or the committer is not replaced.
+/// The tagger of a tag is replaced with the new committer.
+pub(crate) type Replacements = HashMap<String, (Option<Author>, Option<Author>)>;
+
+/// Rewrite the author, committer and tagger lines in the output of
+/// `git fast-export --show-original-ids` and write it for `git fast-import`.
+pub(crate) fn rewrite_stream<R, W>(
+    mut input: R,
+    mut output: W,
+    replacements: &Replacements,
+) -> io::Result<()>
+where
+    R: BufRead,
+    W: Write,
+{
+    let mut line = Vec::new();
+    let mut current = None;
+    loop {
+        line.clear();
+        if input.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+
+        if line.starts_with(b"commit ") || line.starts_with(b"tag ") || line.starts_with(b"reset ")
+        {
+            current = None;
+        } else if let Some(oid) = line.strip_prefix(b"original-oid ") {
+            current = std::str::from_utf8(oid)
+                .ok()
+                .and_then(|oid| replacements.get(oid.trim_end()));
+        } else if let Some(size) = line.strip_prefix(b"data ") {
+            // The message may contain lines which look like commands.
+            let size: u64 = std::str::from_utf8(size)
+                .ok()
+                .and_then(|size| size.trim_end().parse().ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid data size"))?;
+            output.write_all(&line)?;
+            if io::copy(&mut (&mut input).take(size), &mut output)? != size {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            continue;
+        } else if let Some((new_author, new_committer)) = current {
+            if let (Some(author), true) = (new_author, line.starts_with(b"author ")) {
+                line = replace_ident(&line, "author", author)?;
+            } else if let (Some(committer), true) = (new_committer, line.starts_with(b"committer "))
+            {
+                line = replace_ident(&line, "committer", committer)?;
+            } else if let (Some(tagger), true) = (new_committer, line.starts_with(b"tagger ")) {
+                line = replace_ident(&line, "tagger", tagger)?;
+            }
+        }
+        output.write_all(&line)?;
+    }
+    output.flush()
+}
+
+/// Check that `author` can be written in an ident line.
+/// `\n`, `<` and `>` would break the stream of `git fast-import`.
+pub(crate) fn check_ident(author: &Author) -> io::Result<()> {
+    let fields = [author.name(), author.email()];
+    match fields
+        .iter()
+        .filter_map(|field| field.as_deref())
+        .find(|field| field.contains(&['\n', '<', '>'][..]))
+    {
+        Some(field) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{:?} cannot be written in an ident line", field),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// `<command> <name> <<email>> <time> <offset>` with the new name and email.
+fn replace_ident(line: &[u8], command: &str, author: &Author) -> io::Result<Vec<u8>> {
+    check_ident(author)?;
+    let date = line
+        .iter()
+        .rposition(|&c| c == b'>')
+        .map(|i| &line[i + 1..])
+        .unwrap_or(b"\n");
+    let mut replaced = format!(
+        "{} {} <{}>",
+        command,
+        author.name().as_deref().unwrap_or_default(),
+        author.email().as_deref().unwrap_or_default()
+    )
+    .into_bytes();
+    replaced.extend_from_slice(date);
+    Ok(replaced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn author(name: &str, email: &str) -> Author {
+        Author::from_raw(name, email)
+    }
+
+    /// `data <size>` and the message
+    fn data(message: &str) -> String {
+        format!("data {}\n{}", message.len(), message)
+    }
+
+    #[test]
+    fn rewrite_commits_and_tags() {
+        // The message has lines which look like commands, and the size counts the bytes.
+        let message = "author Fake <fake@example.com> 0 +0000\ncommitter 日本\n";
+        let input = [
+            "reset refs/heads/main\n".to_string(),
+            "commit refs/heads/main\n".to_string(),
+            "mark :1\n".to_string(),
+            "original-oid aaaa\n".to_string(),
+            "author Old <old@example.com> 1700000000 +0900\n".to_string(),
+            "committer Old <old@example.com> 1700000001 -0130\n".to_string(),
+            data(message),
+            "\n".to_string(),
+            "commit refs/heads/main\n".to_string(),
+            "mark :2\n".to_string(),
+            "original-oid bbbb\n".to_string(),
+            "from :1\n".to_string(),
+            "author Kept <kept@example.com> 1700000002 +0000\n".to_string(),
+            "committer Kept <kept@example.com> 1700000002 +0000\n".to_string(),
+            data("kept\n"),
+            "\n".to_string(),
+            "tag v1\n".to_string(),
+            "from :2\n".to_string(),
+            "original-oid cccc\n".to_string(),
+            "tagger Old <old@example.com> 1700000003 +0900\n".to_string(),
+            data("v1\n"),
+            "tag v2\n".to_string(),
+            "from :2\n".to_string(),
+            "original-oid dddd\n".to_string(),
+            "tagger Old <old@example.com> 1700000004 +0900\n".to_string(),
+            data("v2\n"),
+        ]
+        .concat();
+        let new = author("New", "new@example.com");
+        let replacements: Replacements = vec![
+            ("aaaa".to_string(), (Some(new.clone()), None)),
+            ("cccc".to_string(), (None, Some(new))),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut output = vec![];
+        rewrite_stream(input.as_bytes(), &mut output, &replacements).unwrap();
+        let expected = input
+            .replacen(
+                "author Old <old@example.com> 1700000000",
+                "author New <new@example.com> 1700000000",
+                1,
+            )
+            .replacen(
+                "tagger Old <old@example.com> 1700000003",
+                "tagger New <new@example.com> 1700000003",
+                1,
+            );
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn rewrite_stream_rejects_short_data() {
+        let input = "commit refs/heads/main\noriginal-oid aaaa\ndata 10\nshort";
+        let error = rewrite_stream(input.as_bytes(), vec![], &Replacements::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn replace_ident_keeps_date() {
+        let line = b"author Old <old@example.com> 1700000000 +0900\n";
+        let replaced = replace_ident(line, "author", &author("New", "new@example.com")).unwrap();
+        assert_eq!(
+            replaced,
+            b"author New <new@example.com> 1700000000 +0900\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn replace_ident_refuses_broken_idents() {
+        let line = b"author Old <old@example.com> 1700000000 +0900\n";
+        for (name, email) in &[
+            ("Evil\ndata 3", "new@example.com"),
+            ("Evil <x@y>", "new@example.com"),
+            ("New", "new@example.com>"),
+            ("New", "<new@example.com"),
+        ] {
+            let error = replace_ident(line, "author", &author(name, email)).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Parser of the filter expression of `replace detail --where`.
+//!
+//! ```text
+//! expression := and ("or" and)*
+//! and        := unary ("and" unary)*
+//! unary      := "not" unary | "(" expression ")" | predicate
+//! predicate  := role ("." field)? ("=" | "!=" | "=~") value | "path" ("=" | "!=") value
+//! role       := "author" | "committer"
+//! field      := "name" | "email" | "email-ignore-case" | "email-domain"
+//! ```
+//!
+//! `author = value` matches `Name <email>`, `Name` or `<email>`, and `author =~ value` is a regex.
+//! `path = pathspec` matches the commits which change a file in the pathspec.
+//! A value which has spaces, parentheses, `=` or `!` must be quoted with `'` or `"`.
+
+use super::{author::split_ident, Author, IdentityMatcher, ReplaceFilter};
+use crate::error::FilterParseError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Operator(&'static str),
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::Word(word) | Self::Quoted(word) => write!(f, "{}", word),
+            Self::Operator(operator) => write!(f, "{}", operator),
+            Self::Open => write!(f, "("),
+            Self::Close => write!(f, ")"),
+        }
+    }
+}
+
+const OPERATORS: [&str; 3] = ["!=", "=~", "="];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, FilterParseError> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::Open);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::Close);
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some(next) if next == c => break,
+                    Some('\\') if c == '"' => {
+                        value.push(chars.next().ok_or(FilterParseError::UnterminatedQuote)?)
+                    }
+                    Some(next) => value.push(next),
+                    None => return Err(FilterParseError::UnterminatedQuote),
+                }
+            }
+            tokens.push(Token::Quoted(value));
+        } else if let Some(operator) = OPERATORS
+            .iter()
+            .find(|o| chars.clone().take(o.len()).eq(o.chars()))
+        {
+            for _ in 0..operator.len() {
+                chars.next();
+            }
+            tokens.push(Token::Operator(operator));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' || c == '=' || c == '!' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            if word.is_empty() {
+                return Err(FilterParseError::Unexpected(c.to_string()));
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, FilterParseError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or(FilterParseError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expression(&mut self) -> Result<ReplaceFilter, FilterParseError> {
+        let mut filter = self.and()?;
+        while self.is_keyword("or") {
+            self.position += 1;
+            filter = filter.or(self.and()?);
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<ReplaceFilter, FilterParseError> {
+        let mut filter = self.unary()?;
+        while self.is_keyword("and") {
+            self.position += 1;
+            filter = filter.and(self.unary()?);
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<ReplaceFilter, FilterParseError> {
+        if self.is_keyword("not") {
+            self.position += 1;
+            return Ok(!self.unary()?);
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            let filter = self.expression()?;
+            return match self.next()? {
+                Token::Close => Ok(filter),
+                token => Err(FilterParseError::Unexpected(token.to_string())),
+            };
+        }
+        self.predicate()
+    }
+
+    fn predicate(&mut self) -> Result<ReplaceFilter, FilterParseError> {
+        let key = match self.next()? {
+            Token::Word(word) => word,
+            token => return Err(FilterParseError::Unexpected(token.to_string())),
+        };
+        let operator = match self.next()? {
+            Token::Operator(operator) => operator,
+            token => return Err(FilterParseError::Unexpected(token.to_string())),
+        };
+        let value = match self.next()? {
+            Token::Word(value) | Token::Quoted(value) => value,
+            token => return Err(FilterParseError::Unexpected(token.to_string())),
+        };
+
+        if key == "path" {
+            let filter = match operator {
+                "=" | "!=" => ReplaceFilter::Path(value),
+                _ => return Err(FilterParseError::Unexpected(operator.to_string())),
+            };
+            return Ok(if operator == "!=" { !filter } else { filter });
+        }
+
+        let (role, field) = match key.split_once('.') {
+            Some((role, field)) => (role, Some(field)),
+            None => (key.as_str(), None),
+        };
+        let matcher = match (field, operator) {
+            (None, "=~") => IdentityMatcher::regex(&value)?,
+            (None, _) => parse_author(&value).into(),
+            (Some("name"), "=" | "!=") => IdentityMatcher::Name(value),
+            (Some("email"), "=" | "!=") => IdentityMatcher::Email(value),
+            (Some("email-ignore-case"), "=" | "!=") => IdentityMatcher::EmailIgnoreCase(value),
+            (Some("email-domain"), "=" | "!=") => IdentityMatcher::email_domain(value),
+            (Some(_), "=~") => return Err(FilterParseError::Unexpected(operator.to_string())),
+            (Some(_), _) => return Err(FilterParseError::UnknownField(key.clone())),
+        };
+        let filter = match role {
+            "author" => ReplaceFilter::author(matcher),
+            "committer" => ReplaceFilter::committer(matcher),
+            _ => return Err(FilterParseError::UnknownField(key.clone())),
+        };
+        Ok(if operator == "!=" { !filter } else { filter })
+    }
+}
+
+/// `Name <email>`, `<email>` or `Name`
+fn parse_author(text: &str) -> Author {
+    match split_ident(text) {
+        Some((name, email, _)) => Author::from_raw(name, email),
+        None => Author::from_raw(text.trim(), ""),
+    }
+}
+
+pub(crate) fn parse(text: &str) -> Result<ReplaceFilter, FilterParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+    };
+    let filter = parser.expression()?;
+    match parser.peek() {
+        Some(token) => Err(FilterParseError::Unexpected(token.to_string())),
+        None => Ok(filter),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(text: &str) -> String {
+        parse(text).unwrap().to_string()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            parsed("author=a or author=b and not author=c"),
+            "(author name `a`) or ((author name `b`) and (not (author name `c`)))"
+        );
+        assert_eq!(
+            parsed("author=a or author=b and not author=c"),
+            parsed("author=a or (author=b and (not author=c))")
+        );
+        assert_ne!(
+            parsed("author=a or author=b and author=c"),
+            parsed("(author=a or author=b) and author=c")
+        );
+    }
+
+    #[test]
+    fn keywords_ignore_case() {
+        assert_eq!(
+            parsed("author=a OR NOT author=b And author=c"),
+            parsed("author=a or not author=b and author=c")
+        );
+    }
+
+    #[test]
+    fn quoted_values() {
+        assert_eq!(
+            parsed(r#"author = "Foo Bar <foo@example.com>""#),
+            "author Foo Bar <foo@example.com>"
+        );
+        assert_eq!(
+            parsed(r#"author.name = "say \"hi\" (or not)""#),
+            r#"author name `say "hi" (or not)`"#
+        );
+        assert_eq!(parsed(r"author.name='a\b'"), r"author name `a\b`");
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(
+            parsed("author.email!=a@b.com"),
+            "not (author email `a@b.com`)"
+        );
+        assert_eq!(parsed("committer =~ ^bot"), "committer regex `^bot`");
+        assert_eq!(parsed("path!=vendor/"), "not (path `vendor/`)");
+        assert_eq!(parsed("path=src"), "path `src`");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            parse("author='foo"),
+            Err(FilterParseError::UnterminatedQuote)
+        ));
+        assert!(matches!(
+            parse(r#"author="foo\"#),
+            Err(FilterParseError::UnterminatedQuote)
+        ));
+        assert!(matches!(
+            parse("author=a)"),
+            Err(FilterParseError::Unexpected(token)) if token == ")"
+        ));
+        assert!(matches!(
+            parse("(author=a"),
+            Err(FilterParseError::UnexpectedEnd)
+        ));
+        assert!(matches!(parse(""), Err(FilterParseError::UnexpectedEnd)));
+        assert!(matches!(
+            parse("author="),
+            Err(FilterParseError::UnexpectedEnd)
+        ));
+        assert!(matches!(
+            parse("author.phone=1"),
+            Err(FilterParseError::UnknownField(field)) if field == "author.phone"
+        ));
+        assert!(matches!(
+            parse("reviewer=a"),
+            Err(FilterParseError::UnknownField(field)) if field == "reviewer"
+        ));
+        assert!(matches!(
+            parse("path=~src"),
+            Err(FilterParseError::Unexpected(_))
+        ));
+        assert!(matches!(
+            parse("author.name=~a"),
+            Err(FilterParseError::Unexpected(_))
+        ));
+        assert!(matches!(
+            parse("author=a author=b"),
+            Err(FilterParseError::Unexpected(_))
+        ));
+        assert!(matches!(
+            parse("author=~("),
+            Err(FilterParseError::Unexpected(_))
+        ));
+    }
+}
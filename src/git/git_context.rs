@@ -0,0 +1,189 @@
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The repository and the git binary which the git commands run with.
+///
+/// The default runs `git` in the current directory, like `git` itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitContext {
+    path: Option<PathBuf>,
+    git_dir: Option<PathBuf>,
+    work_tree: Option<PathBuf>,
+    git: PathBuf,
+    envs: Vec<(OsString, OsString)>,
+}
+
+impl Default for GitContext {
+    fn default() -> Self {
+        GitContext {
+            path: None,
+            git_dir: None,
+            work_tree: None,
+            git: PathBuf::from("git"),
+            envs: vec![],
+        }
+    }
+}
+
+impl GitContext {
+    /// Run git in `path` as `git -C <path>`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        GitContext {
+            path: Some(path.into()),
+            ..Default::default()
+        }
+    }
+
+    /// `--git-dir`. A relative path is from the path of the context.
+    pub fn with_git_dir<P: Into<PathBuf>>(mut self, git_dir: P) -> Self {
+        self.git_dir = Some(git_dir.into());
+        self
+    }
+
+    /// `--work-tree`. A relative path is from the path of the context.
+    pub fn with_work_tree<P: Into<PathBuf>>(mut self, work_tree: P) -> Self {
+        self.work_tree = Some(work_tree.into());
+        self
+    }
+
+    /// The git binary. e.g. `/usr/local/bin/git`
+    pub fn with_git<P: Into<PathBuf>>(mut self, git: P) -> Self {
+        self.git = git.into();
+        self
+    }
+
+    /// An environment variable which is set to the git commands in addition to the process ones.
+    pub fn with_env<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<OsString>,
+        V: Into<OsString>,
+    {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn git_dir(&self) -> Option<&Path> {
+        self.git_dir.as_deref()
+    }
+
+    pub fn work_tree(&self) -> Option<&Path> {
+        self.work_tree.as_deref()
+    }
+
+    pub fn git(&self) -> &Path {
+        &self.git
+    }
+
+    pub fn envs(&self) -> &[(OsString, OsString)] {
+        &self.envs
+    }
+
+    /// A git command in the repository. The subcommand and its arguments are added by the caller.
+    pub(crate) fn command(&self) -> Command {
+        let mut command = Command::new(&self.git);
+        if let Some(path) = &self.path {
+            command.arg("-C").arg(path);
+        }
+        if let Some(git_dir) = &self.git_dir {
+            command.arg("--git-dir").arg(git_dir);
+        }
+        if let Some(work_tree) = &self.work_tree {
+            command.arg("--work-tree").arg(work_tree);
+        }
+        command.envs(self.envs.iter().map(|(k, v)| (k, v)));
+        command
+    }
+
+    /// The value of the environment variable which the git commands see.
+    pub(crate) fn env_var<K: AsRef<OsStr>>(&self, key: K) -> Option<OsString> {
+        let key = key.as_ref();
+        match self.envs.iter().rev().find(|(k, _)| k == key) {
+            Some((_, value)) => Some(value.clone()),
+            None => std::env::var_os(key),
+        }
+    }
+
+    /// `path` which git printed relative to the path of the context. e.g. `.git`
+    pub(crate) fn resolve(&self, path: PathBuf) -> PathBuf {
+        match &self.path {
+            Some(base) if path.is_relative() => base.join(path),
+            _ => path,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_runs_git_in_current_directory() {
+        let context = GitContext::default();
+        let command = context.command();
+        assert_eq!(command.get_program(), "git");
+        assert_eq!(command.get_args().count(), 0);
+    }
+
+    #[test]
+    fn global_options() {
+        let context = GitContext::new("repo")
+            .with_git_dir("repo.git")
+            .with_work_tree("tree")
+            .with_git("/usr/local/bin/git");
+        let command = context.command();
+        assert_eq!(command.get_program(), "/usr/local/bin/git");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            ["-C", "repo", "--git-dir", "repo.git", "--work-tree", "tree"]
+        );
+    }
+
+    #[test]
+    fn later_env_takes_precedence() {
+        let context = GitContext::default()
+            .with_env("GIT_AUTHOR_TEST_KEY", "first")
+            .with_env("GIT_AUTHOR_TEST_KEY", "second")
+            .with_env("PATH", "/nowhere");
+        assert_eq!(
+            context.env_var("GIT_AUTHOR_TEST_KEY"),
+            Some(OsString::from("second"))
+        );
+        assert_eq!(context.env_var("PATH"), Some(OsString::from("/nowhere")));
+        assert_eq!(context.env_var("GIT_AUTHOR_TEST_MISSING"), None);
+        assert_eq!(
+            GitContext::default().env_var("PATH"),
+            std::env::var_os("PATH")
+        );
+
+        let command = context.command();
+        let value = command
+            .get_envs()
+            .filter(|(k, _)| *k == "GIT_AUTHOR_TEST_KEY")
+            .last()
+            .and_then(|(_, v)| v);
+        assert_eq!(value, Some(OsStr::new("second")));
+    }
+
+    #[test]
+    fn resolve_relative_to_path() {
+        let context = GitContext::new("/repo");
+        assert_eq!(
+            context.resolve(PathBuf::from(".git")),
+            PathBuf::from("/repo/.git")
+        );
+        assert_eq!(
+            context.resolve(PathBuf::from("/other/.git")),
+            PathBuf::from("/other/.git")
+        );
+        assert_eq!(
+            GitContext::default().resolve(PathBuf::from(".git")),
+            PathBuf::from(".git")
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use super::{author::split_ident, time_to_text, Author};
+use crate::error::AuthorParseError;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// An author or a committer with the date, as git records it in a commit.
+/// e.g. `Name <email> 1700000000 +0900`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Ident {
+    author: Author,
+    /// seconds since the unix epoch
+    time: i64,
+    /// minutes east of UTC
+    offset: i32,
+}
+
+impl Ident {
+    pub fn new(author: Author, time: i64, offset: i32) -> Self {
+        Ident {
+            author,
+            time,
+            offset,
+        }
+    }
+
+    pub fn author(&self) -> &Author {
+        &self.author
+    }
+
+    pub fn into_author(self) -> Author {
+        self.author
+    }
+
+    /// Seconds since the unix epoch.
+    pub fn time(&self) -> i64 {
+        self.time
+    }
+
+    /// Timezone offset in minutes east of UTC. e.g. `540` for `+0900`
+    pub fn offset(&self) -> i32 {
+        self.offset
+    }
+
+    /// e.g. `+0900`
+    pub fn offset_text(&self) -> String {
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        let minutes = self.offset.abs();
+        format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+    }
+
+    /// e.g. `2023-11-14 22:13:20 UTC`
+    pub fn time_text(&self) -> String {
+        time_to_text(self.time)
+    }
+}
+
+/// Minutes east of UTC of `+hhmm` or `-hhmm`.
+fn parse_offset(text: &str) -> Option<i32> {
+    let (sign, digits) = match text.split_at(text.len().min(1)) {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+impl FromStr for Ident {
+    type Err = AuthorParseError;
+
+    /// Parse `Name <email> 1700000000 +0900`. The name and the email may be empty.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (name, email, date) =
+            split_ident(text).ok_or_else(|| AuthorParseError::InvalidFormat(text.to_string()))?;
+        let invalid_date = || AuthorParseError::InvalidDate(date.to_string());
+        let (time, offset) = date.split_once(' ').ok_or_else(invalid_date)?;
+        Ok(Ident {
+            author: Author::from_raw(name, email),
+            time: time.parse().map_err(|_| invalid_date())?,
+            offset: parse_offset(offset.trim()).ok_or_else(invalid_date)?,
+        })
+    }
+}
+
+impl std::fmt::Display for Ident {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let name = self.author.name().as_deref().unwrap_or_default();
+        let email = self.author.email().as_deref().unwrap_or_default();
+        write!(
+            f,
+            "{} <{}> {} {}",
+            name,
+            email,
+            self.time,
+            self.offset_text()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ident() {
+        let ident: Ident = "Foo Bar <foo@example.com> 1700000000 +0900"
+            .parse()
+            .unwrap();
+        assert_eq!(ident.author().name().as_deref(), Some("Foo Bar"));
+        assert_eq!(ident.author().email().as_deref(), Some("foo@example.com"));
+        assert_eq!(ident.time(), 1700000000);
+        assert_eq!(ident.offset(), 540);
+        assert_eq!(
+            ident.to_string(),
+            "Foo Bar <foo@example.com> 1700000000 +0900"
+        );
+    }
+
+    #[test]
+    fn parse_negative_offset() {
+        let ident: Ident = "Foo <foo@example.com> 0 -0130".parse().unwrap();
+        assert_eq!(ident.offset(), -90);
+        assert_eq!(ident.offset_text(), "-0130");
+    }
+
+    #[test]
+    fn parse_empty_name_and_email() {
+        let ident: Ident = " <> 1700000000 +0000".parse().unwrap();
+        assert_eq!(ident.author().name(), &None);
+        assert_eq!(ident.author().email(), &None);
+    }
+
+    #[test]
+    fn parse_malformed_dates() {
+        for text in &[
+            "Foo <foo@example.com>",
+            "Foo <foo@example.com> 1700000000",
+            "Foo <foo@example.com> now +0900",
+            "Foo <foo@example.com> 1700000000 0900",
+            "Foo <foo@example.com> 1700000000 +09",
+            "Foo <foo@example.com> 1700000000 +09ab",
+        ] {
+            assert!(
+                matches!(text.parse::<Ident>(), Err(AuthorParseError::InvalidDate(_))),
+                "{}",
+                text
+            );
+        }
+        assert!(matches!(
+            "Foo 1700000000 +0900".parse::<Ident>(),
+            Err(AuthorParseError::InvalidFormat(_))
+        ));
+    }
+}
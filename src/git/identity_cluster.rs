@@ -0,0 +1,288 @@
+use super::{Author, IdentityStats, MailmapEntry, MapScope, ReplaceMapEntry};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Local parts which many people share, so they do not make identities similar.
+const COMMON_LOCAL_PARTS: [&str; 8] = [
+    "root", "admin", "user", "info", "git", "dev", "noreply", "github",
+];
+
+/// Lowercase letters and digits of `text`. e.g. `John.Doe` -> `johndoe`
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Whether `email` looks like `user@hostname` which git makes when `user.email` is not set.
+fn is_auto_generated_email(email: &str) -> bool {
+    let domain = match email.rsplit_once('@') {
+        Some((_, domain)) => domain.to_lowercase(),
+        None => return true,
+    };
+    !domain.contains('.')
+        || domain.contains("(none)")
+        || [".local", ".localdomain", ".lan", ".home", ".internal"]
+            .iter()
+            .any(|suffix| domain.ends_with(suffix))
+}
+
+/// Keys which similar identities share, and why they are similar.
+fn similarity_keys(author: &Author) -> Vec<(String, String)> {
+    let mut keys = vec![];
+    let mut add_name_key = |key: String, reason: String| {
+        if key.chars().count() >= 3 && !COMMON_LOCAL_PARTS.contains(&key.as_str()) {
+            keys.push((key, reason));
+        }
+    };
+    if let Some(name) = author.name() {
+        // An initial and the last name like `jdoe` are not used. `John Doe` and `Jane Doe` share it.
+        add_name_key(
+            normalize(name),
+            format!("similar name `{}`", normalize(name)),
+        );
+    }
+    if let Some(email) = author.email() {
+        let local = email.rsplit_once('@').map_or(email.as_str(), |(l, _)| l);
+        let local = local.split('+').next().unwrap_or(local);
+        add_name_key(
+            normalize(local),
+            format!("similar name `{}`", normalize(local)),
+        );
+        let email = email.to_lowercase();
+        keys.push((format!("<{}>", email), format!("same email `{}`", email)));
+    }
+    keys
+}
+
+fn root(parents: &mut [usize], i: usize) -> usize {
+    let mut i = i;
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Authors and committers which are probably the same person.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IdentityCluster {
+    canonical: Author,
+    members: Vec<IdentityStats>,
+    reasons: Vec<String>,
+}
+
+impl IdentityCluster {
+    /// Group the identities which share an email, a normalized name or an email local part.
+    ///
+    /// Only groups of two or more identities are returned, from the one with the most commits.
+    pub fn detect(stats: &[IdentityStats]) -> Vec<IdentityCluster> {
+        let mut parents: Vec<_> = (0..stats.len()).collect();
+        let mut first_index = HashMap::new();
+        let mut reasons: HashMap<String, String> = HashMap::new();
+        let mut key_counts: HashMap<String, usize> = HashMap::new();
+        for (i, s) in stats.iter().enumerate() {
+            let mut keys = similarity_keys(s.identity());
+            keys.sort();
+            keys.dedup_by(|a, b| a.0 == b.0);
+            for (key, reason) in keys {
+                let j = *first_index.entry(key.clone()).or_insert(i);
+                let (root_i, root_j) = (root(&mut parents, i), root(&mut parents, j));
+                parents[root_i] = root_j;
+                *key_counts.entry(key.clone()).or_default() += 1;
+                reasons.entry(key).or_insert(reason);
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<IdentityStats>> = HashMap::new();
+        for (i, s) in stats.iter().enumerate() {
+            groups
+                .entry(root(&mut parents, i))
+                .or_default()
+                .push(s.clone());
+        }
+        let mut group_reasons: HashMap<usize, Vec<String>> = HashMap::new();
+        for (key, i) in &first_index {
+            if key_counts[key] >= 2 {
+                group_reasons
+                    .entry(root(&mut parents, *i))
+                    .or_default()
+                    .push(reasons[key].clone());
+            }
+        }
+
+        let mut clusters: Vec<_> = groups
+            .into_iter()
+            .filter(|(_, members)| members.len() >= 2)
+            .map(|(group, mut members)| {
+                members.sort_by_key(|s| std::cmp::Reverse(s.commit_count()));
+                let mut reasons = group_reasons.remove(&group).unwrap_or_default();
+                reasons.extend(
+                    members
+                        .iter()
+                        .filter_map(|s| s.identity().email().as_ref())
+                        .filter(|email| is_auto_generated_email(email))
+                        .map(|email| format!("auto-generated email `{}`", email)),
+                );
+                reasons.sort();
+                IdentityCluster {
+                    canonical: propose_canonical(members.iter().map(|s| s.identity())),
+                    members,
+                    reasons,
+                }
+            })
+            .collect();
+        clusters.sort_by_key(|c| std::cmp::Reverse(c.commit_count()));
+        clusters
+    }
+
+    /// The proposed identity of the person.
+    /// The full name and the email which is not auto-generated are preferred,
+    /// and then the ones with more commits.
+    pub fn canonical(&self) -> &Author {
+        &self.canonical
+    }
+
+    /// The identities in the cluster from the one with the most commits.
+    pub fn members(&self) -> &[IdentityStats] {
+        &self.members
+    }
+
+    /// Why the identities are grouped. e.g. ``same email `foo@example.com` ``
+    pub fn reasons(&self) -> &[String] {
+        &self.reasons
+    }
+
+    /// The number of the commits of all the identities.
+    pub fn commit_count(&self) -> usize {
+        self.members.iter().map(|s| s.commit_count()).sum()
+    }
+
+    /// Entries which replace the other identities with the canonical one.
+    /// Identities without a name or an email are skipped.
+    /// Nothing is returned if the canonical email is not valid, since `ReplaceMap::load` rejects it.
+    pub fn to_map_entries(&self) -> Vec<ReplaceMapEntry> {
+        if self.canonical.has_none_field()
+            || Author::new(
+                self.canonical.name().as_ref(),
+                self.canonical.email().as_ref(),
+            )
+            .is_err()
+        {
+            return vec![];
+        }
+        self.others()
+            .filter(|old| !old.has_none_field())
+            .map(|old| ReplaceMapEntry::new(old.clone(), self.canonical.clone(), MapScope::Both))
+            .collect()
+    }
+
+    /// `.mailmap` entries which map the other identities to the canonical one.
+    pub fn to_mailmap_entries(&self) -> Vec<MailmapEntry> {
+        self.others()
+            .filter_map(|old| MailmapEntry::from_authors(&self.canonical, old))
+            .collect()
+    }
+
+    fn others(&self) -> impl Iterator<Item = &Author> {
+        self.members
+            .iter()
+            .map(|s| s.identity())
+            .filter(move |identity| **identity != self.canonical)
+    }
+}
+
+/// The best name and the best email of the identities, which are sorted by the commits.
+/// A full name and an email which is not auto-generated are preferred.
+pub(crate) fn propose_canonical<'a, I>(identities: I) -> Author
+where
+    I: IntoIterator<Item = &'a Author>,
+    I::IntoIter: Clone,
+{
+    let identities = identities.into_iter();
+    let name = identities
+        .clone()
+        .filter_map(|identity| identity.name().as_deref())
+        .enumerate()
+        .max_by_key(|(i, name)| (name.split_whitespace().count() >= 2, std::cmp::Reverse(*i)))
+        .map(|(_, name)| name);
+    let email = identities
+        .filter_map(|identity| identity.email().as_deref())
+        .enumerate()
+        .max_by_key(|(i, email)| (!is_auto_generated_email(email), std::cmp::Reverse(*i)))
+        .map(|(_, email)| email);
+    Author::from_raw(name.unwrap_or_default(), email.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{Commit, ReplaceMap};
+
+    fn stats(name: &str, email: &str, commit_count: usize) -> IdentityStats {
+        let fields: Vec<String> = [
+            "0", name, email, name, email, "subject", "0", "0", "", "message",
+        ]
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+        let mut stats = IdentityStats::new(Author::from_raw(name, email));
+        for _ in 0..commit_count {
+            stats.add(&Commit::from_fields(&fields));
+        }
+        stats
+    }
+
+    #[test]
+    fn prefer_email_which_is_not_auto_generated() {
+        let clusters = IdentityCluster::detect(&[
+            stats("John Doe", "jd@laptop.local", 10),
+            stats("john", "john@vm.(none)", 5),
+            stats("John Doe", "john@example.com", 1),
+        ]);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(
+            clusters[0].canonical(),
+            &Author::from_raw("John Doe", "john@example.com")
+        );
+    }
+
+    #[test]
+    fn emitted_map_can_be_loaded() {
+        let clusters = IdentityCluster::detect(&[
+            stats("John Doe", "john@example.com", 10),
+            stats("john", "john@vm.(none)", 5),
+            stats("John Doe", "jd@laptop.local", 1),
+        ]);
+        let entries: Vec<_> = clusters.iter().flat_map(|c| c.to_map_entries()).collect();
+        assert_eq!(entries.len(), 2);
+        let map = ReplaceMap::new(entries);
+
+        let path =
+            std::env::temp_dir().join(format!("git-author-{}-emit.toml", std::process::id()));
+        std::fs::write(&path, map.to_toml().unwrap()).unwrap();
+        let loaded = ReplaceMap::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), map);
+    }
+
+    #[test]
+    fn skip_invalid_canonical_email() {
+        let clusters = IdentityCluster::detect(&[
+            stats("john", "john@vm.(none)", 5),
+            stats("john", "john@host.(none)", 1),
+        ]);
+        assert_eq!(clusters.len(), 1);
+        assert!(clusters[0].to_map_entries().is_empty());
+    }
+
+    #[test]
+    fn do_not_group_by_initial_and_last_name() {
+        let clusters = IdentityCluster::detect(&[
+            stats("John Smith", "john@a.com", 1),
+            stats("Jane Smith", "jane@b.com", 1),
+        ]);
+        assert!(clusters.is_empty());
+    }
+}
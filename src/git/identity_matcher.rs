@@ -0,0 +1,238 @@
+use super::{quote_shell, Author};
+use crate::error::{AuthorFieldError, AuthorHasNoneField, ConditionTextError};
+use regex::Regex;
+use serde::Serialize;
+
+/// Which author or committer a filter matches.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityMatcher {
+    /// the same name and email
+    Exact(Author),
+    /// the same name
+    Name(String),
+    /// the same email
+    Email(String),
+    /// the same email ignoring ASCII case
+    EmailIgnoreCase(String),
+    /// the email in the domain ignoring ASCII case. e.g. `example.com`
+    EmailDomain(String),
+    /// `Name <email>` matches the regex
+    Regex(#[serde(serialize_with = "super::serialize_regex")] Regex),
+}
+
+impl From<Author> for IdentityMatcher {
+    /// `Name` or `Email` if the author has only one of them.
+    fn from(author: Author) -> Self {
+        match (author.name(), author.email()) {
+            (Some(name), None) => Self::Name(name.clone()),
+            (None, Some(email)) => Self::Email(email.clone()),
+            _ => Self::Exact(author),
+        }
+    }
+}
+
+impl std::fmt::Display for IdentityMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::Exact(author) => write!(f, "{}", author),
+            Self::Name(name) => write!(f, "name `{}`", name),
+            Self::Email(email) => write!(f, "email `{}`", email),
+            Self::EmailIgnoreCase(email) => write!(f, "email `{}` (ignoring case)", email),
+            Self::EmailDomain(domain) => write!(f, "email domain `{}`", domain),
+            Self::Regex(regex) => write!(f, "regex `{}`", regex),
+        }
+    }
+}
+
+impl IdentityMatcher {
+    /// `domain` may start with `@`.
+    pub fn email_domain<S: AsRef<str>>(domain: S) -> Self {
+        Self::EmailDomain(domain.as_ref().trim_start_matches('@').to_string())
+    }
+
+    pub fn regex<S: AsRef<str>>(regex: S) -> Result<Self, regex::Error> {
+        Ok(Self::Regex(Regex::new(regex.as_ref())?))
+    }
+
+    pub fn matches(&self, author: &Author) -> bool {
+        let email = author.email().as_deref();
+        match self {
+            Self::Exact(expected) => author == expected,
+            Self::Name(name) => author.name().as_ref() == Some(name),
+            Self::Email(expected) => email == Some(expected),
+            Self::EmailIgnoreCase(expected) => {
+                email.is_some_and(|email| email.eq_ignore_ascii_case(expected))
+            }
+            Self::EmailDomain(domain) => email
+                .and_then(|email| email.rsplit_once('@'))
+                .is_some_and(|(_, d)| d.eq_ignore_ascii_case(domain)),
+            Self::Regex(regex) => regex.is_match(&author.to_string()),
+        }
+    }
+
+    /// Condition of `test` in the env-filter script.
+    /// `prefix` is `GIT_AUTHOR` or `GIT_COMMITTER`.
+    pub fn to_condition_text(&self, prefix: &str) -> Result<String, ConditionTextError> {
+        // `x` prevents `test` from taking a value like `!` or `-n` as an operator.
+        let equals = |variable: &str, value: &str| {
+            format!(
+                r#""x{}" = {}"#,
+                variable,
+                quote_shell(&format!("x{}", value))
+            )
+        };
+        let lowercase = |variable: &str| {
+            format!(
+                r#"$(printf '%s' "{}" | tr '[:upper:]' '[:lower:]')"#,
+                variable
+            )
+        };
+        let name = format!("${}_NAME", prefix);
+        let email = format!("${}_EMAIL", prefix);
+
+        match self {
+            Self::Exact(author) => match (author.name(), author.email()) {
+                (Some(n), Some(e)) => Ok(format!("{} -a {}", equals(&name, n), equals(&email, e))),
+                (_, _) => Err(AuthorHasNoneField(AuthorFieldError::new(author)?).into()),
+            },
+            Self::Name(n) => Ok(equals(&name, n)),
+            Self::Email(e) => Ok(equals(&email, e)),
+            Self::EmailIgnoreCase(e) => Ok(equals(&lowercase(&email), &e.to_ascii_lowercase())),
+            Self::EmailDomain(domain) => Ok(format!(
+                r#""x${{{prefix}_EMAIL#*@}}" != "x{email}" -a {}"#,
+                equals(
+                    &lowercase(&format!("${{{}_EMAIL##*@}}", prefix)),
+                    &domain.to_ascii_lowercase()
+                ),
+                prefix = prefix,
+                email = email
+            )),
+            Self::Regex(_) => Err(ConditionTextError::UnsupportedByFilterBranch(
+                self.to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn author(name: &str, email: &str) -> Author {
+        Author::from_raw(name, email)
+    }
+
+    fn authors() -> Vec<Author> {
+        vec![
+            author("Foo Bar", "foo@example.com"),
+            author("Foo Bar", "FOO@Example.COM"),
+            author("foo bar", "foo@sub.example.com"),
+            author("Foo Bar", "foo@example.com.evil"),
+            author("-n", "!"),
+            author("Foo Bar", "no-at-sign"),
+        ]
+    }
+
+    /// Whether the env-filter condition is true for `author`.
+    fn shell_matches(condition: &str, author: &Author) -> bool {
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(format!("if [ {} ]; then echo 1; fi", condition))
+            .env("GIT_AUTHOR_NAME", author.name().clone().unwrap_or_default())
+            .env(
+                "GIT_AUTHOR_EMAIL",
+                author.email().clone().unwrap_or_default(),
+            )
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        output.stdout == b"1\n"
+    }
+
+    #[test]
+    fn from_author() {
+        assert!(matches!(
+            IdentityMatcher::from(author("Foo", "")),
+            IdentityMatcher::Name(name) if name == "Foo"
+        ));
+        assert!(matches!(
+            IdentityMatcher::from(author("", "foo@example.com")),
+            IdentityMatcher::Email(email) if email == "foo@example.com"
+        ));
+        assert!(matches!(
+            IdentityMatcher::from(author("Foo", "foo@example.com")),
+            IdentityMatcher::Exact(_)
+        ));
+    }
+
+    #[test]
+    fn matches_authors() {
+        let cases = [
+            (
+                IdentityMatcher::from(author("Foo Bar", "foo@example.com")),
+                [true, false, false, false, false, false],
+            ),
+            (
+                IdentityMatcher::Name("Foo Bar".to_string()),
+                [true, true, false, true, false, true],
+            ),
+            (
+                IdentityMatcher::Email("foo@example.com".to_string()),
+                [true, false, false, false, false, false],
+            ),
+            (
+                IdentityMatcher::EmailIgnoreCase("Foo@example.com".to_string()),
+                [true, true, false, false, false, false],
+            ),
+            (
+                IdentityMatcher::email_domain("@EXAMPLE.com"),
+                [true, true, false, false, false, false],
+            ),
+            (
+                IdentityMatcher::regex(r"^foo bar <").unwrap(),
+                [false, false, true, false, false, false],
+            ),
+        ];
+        for (matcher, expected) in &cases {
+            let matched: Vec<_> = authors().iter().map(|a| matcher.matches(a)).collect();
+            assert_eq!(matched, expected, "{}", matcher);
+        }
+    }
+
+    #[test]
+    fn condition_text_agrees_with_matches() {
+        let matchers = vec![
+            IdentityMatcher::from(author("Foo Bar", "foo@example.com")),
+            IdentityMatcher::from(author("-n", "!")),
+            IdentityMatcher::Name("Foo Bar".to_string()),
+            IdentityMatcher::Email("foo@example.com".to_string()),
+            IdentityMatcher::EmailIgnoreCase("Foo@example.com".to_string()),
+            IdentityMatcher::email_domain("example.com"),
+        ];
+        for matcher in &matchers {
+            let condition = matcher.to_condition_text("GIT_AUTHOR").unwrap();
+            for author in authors() {
+                assert_eq!(
+                    shell_matches(&condition, &author),
+                    matcher.matches(&author),
+                    "{} {}",
+                    matcher,
+                    author
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn condition_text_errors() {
+        assert!(matches!(
+            IdentityMatcher::regex("foo")
+                .unwrap()
+                .to_condition_text("GIT_AUTHOR"),
+            Err(ConditionTextError::UnsupportedByFilterBranch(_))
+        ));
+        assert!(IdentityMatcher::regex("(").is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use super::{time_to_text, Author, Commit};
+use serde::Serialize;
+
+/// How an author or a committer appears in the history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IdentityStats {
+    identity: Author,
+    commit_count: usize,
+    author_count: usize,
+    committer_count: usize,
+    mismatch_count: usize,
+    first_time: i64,
+    last_time: i64,
+}
+
+impl IdentityStats {
+    pub(crate) fn new(identity: Author) -> Self {
+        IdentityStats {
+            identity,
+            commit_count: 0,
+            author_count: 0,
+            committer_count: 0,
+            mismatch_count: 0,
+            first_time: i64::MAX,
+            last_time: i64::MIN,
+        }
+    }
+
+    /// Count `commit` whose author or committer is the identity.
+    pub(crate) fn add(&mut self, commit: &Commit) {
+        let is_author = *commit.author() == self.identity;
+        let is_committer = *commit.committer() == self.identity;
+        self.commit_count += 1;
+        if commit.author() != commit.committer() {
+            self.mismatch_count += 1;
+        }
+        let times = [
+            (is_author, commit.author_time()),
+            (is_committer, commit.committer_time()),
+        ];
+        for (_, time) in times.iter().filter(|(used, _)| *used) {
+            self.first_time = self.first_time.min(*time);
+            self.last_time = self.last_time.max(*time);
+        }
+        self.author_count += is_author as usize;
+        self.committer_count += is_committer as usize;
+    }
+
+    pub fn identity(&self) -> &Author {
+        &self.identity
+    }
+
+    /// The number of the commits whose author or committer is the identity.
+    pub fn commit_count(&self) -> usize {
+        self.commit_count
+    }
+
+    /// The number of the commits whose author is the identity.
+    pub fn author_count(&self) -> usize {
+        self.author_count
+    }
+
+    /// The number of the commits whose committer is the identity.
+    pub fn committer_count(&self) -> usize {
+        self.committer_count
+    }
+
+    /// The number of the commits of the identity whose author and committer are different.
+    pub fn mismatch_count(&self) -> usize {
+        self.mismatch_count
+    }
+
+    /// The oldest author or committer date of the identity in seconds since the unix epoch.
+    pub fn first_time(&self) -> i64 {
+        self.first_time
+    }
+
+    /// The newest author or committer date of the identity in seconds since the unix epoch.
+    pub fn last_time(&self) -> i64 {
+        self.last_time
+    }
+
+    /// `first_time` as `YYYY-MM-DD hh:mm:ss UTC`
+    pub fn first_time_text(&self) -> String {
+        time_to_text(self.first_time)
+    }
+
+    /// `last_time` as `YYYY-MM-DD hh:mm:ss UTC`
+    pub fn last_time_text(&self) -> String {
+        time_to_text(self.last_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(
+        author: &Author,
+        committer: &Author,
+        author_time: i64,
+        committer_time: i64,
+    ) -> Commit {
+        let text = |field: &Option<String>| field.clone().unwrap_or_default();
+        let fields = [
+            "0".to_string(),
+            text(author.name()),
+            text(author.email()),
+            text(committer.name()),
+            text(committer.email()),
+            "subject".to_string(),
+            author_time.to_string(),
+            committer_time.to_string(),
+            String::new(),
+            "message".to_string(),
+        ];
+        Commit::from_fields(&fields)
+    }
+
+    #[test]
+    fn count_commits() {
+        let foo = Author::from_raw("Foo", "foo@example.com");
+        let bot = Author::from_raw("Bot", "bot@example.com");
+        let mut stats = IdentityStats::new(foo.clone());
+        assert_eq!(stats.commit_count(), 0);
+
+        stats.add(&commit(&foo, &foo, 200, 300));
+        stats.add(&commit(&foo, &bot, 100, 900));
+        stats.add(&commit(&bot, &foo, 800, 500));
+
+        assert_eq!(stats.identity(), &foo);
+        assert_eq!(stats.commit_count(), 3);
+        assert_eq!(stats.author_count(), 2);
+        assert_eq!(stats.committer_count(), 2);
+        assert_eq!(stats.mismatch_count(), 2);
+        // The dates of the other identity are not counted.
+        assert_eq!(stats.first_time(), 100);
+        assert_eq!(stats.last_time(), 500);
+    }
+
+    #[test]
+    fn time_texts() {
+        let foo = Author::from_raw("Foo", "foo@example.com");
+        let mut stats = IdentityStats::new(foo.clone());
+        stats.add(&commit(&foo, &foo, -1, 951782400));
+        assert_eq!(stats.first_time_text(), "1969-12-31 23:59:59 UTC");
+        assert_eq!(stats.last_time_text(), "2000-02-29 00:00:00 UTC");
+
+        let mut stats = IdentityStats::new(foo.clone());
+        stats.add(&commit(&foo, &foo, 0, 1700000000));
+        assert_eq!(stats.first_time_text(), "1970-01-01 00:00:00 UTC");
+        assert_eq!(stats.last_time_text(), "2023-11-14 22:13:20 UTC");
+    }
+}
@@ -0,0 +1,272 @@
+use super::{quote_shell, Author};
+use crate::error::TemplateError;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// A piece of a template field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    /// `{name}`: the old name
+    Name,
+    /// `{email}`: the old email
+    Email,
+    /// `{localpart}`: the old email before the last `@`
+    Localpart,
+    /// `{domain}`: the old email after the last `@`
+    Domain,
+}
+
+const PLACEHOLDERS: [(&str, Part); 4] = [
+    ("name", Part::Name),
+    ("email", Part::Email),
+    ("localpart", Part::Localpart),
+    ("domain", Part::Domain),
+];
+
+fn parse_field(text: &str) -> Result<Vec<Part>, TemplateError> {
+    let mut parts = vec![];
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut key = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => key.push(c),
+                        None => return Err(TemplateError::UnmatchedBrace),
+                    }
+                }
+                let part = PLACEHOLDERS
+                    .iter()
+                    .find(|(name, _)| *name == key)
+                    .map(|(_, part)| part.clone())
+                    .ok_or(TemplateError::UnknownPlaceholder(key))?;
+                if !literal.is_empty() {
+                    parts.push(Part::Text(std::mem::take(&mut literal)));
+                }
+                parts.push(part);
+            }
+            '}' => return Err(TemplateError::UnmatchedBrace),
+            // They would end the ident line of git.
+            '\n' | '<' | '>' => return Err(TemplateError::InvalidCharacter(c)),
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(Part::Text(literal));
+    }
+    Ok(parts)
+}
+
+fn local_part(email: &str) -> &str {
+    email.rsplit_once('@').map_or(email, |(local, _)| local)
+}
+
+fn domain(email: &str) -> &str {
+    email.rsplit_once('@').map_or("", |(_, domain)| domain)
+}
+
+/// New name and email of an author or a committer made from the old ones.
+///
+/// A field is kept if it is `None`.
+/// A field may have `{name}`, `{email}`, `{localpart}` and `{domain}` of the old author.
+/// e.g. `{localpart}@example.com`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentityTemplate {
+    name: Option<Vec<Part>>,
+    email: Option<Vec<Part>>,
+}
+
+impl From<Author> for IdentityTemplate {
+    /// Replace with the author. A field which the author does not have is kept.
+    fn from(author: Author) -> Self {
+        let literal = |text: &Option<String>| text.as_ref().map(|t| vec![Part::Text(t.clone())]);
+        IdentityTemplate {
+            name: literal(author.name()),
+            email: literal(author.email()),
+        }
+    }
+}
+
+/// The template text of a field. e.g. `{localpart}@example.com`
+fn field_text(parts: &[Part]) -> String {
+    parts
+        .iter()
+        .map(|part| match part {
+            Part::Text(text) => text.replace('{', "{{").replace('}', "}}"),
+            part => {
+                let (name, _) = PLACEHOLDERS.iter().find(|(_, p)| p == part).unwrap();
+                format!("{{{}}}", name)
+            }
+        })
+        .collect()
+}
+
+impl std::fmt::Display for IdentityTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let text = |parts: &Option<Vec<Part>>, kept: &str| {
+            parts.as_deref().map_or(kept.to_string(), field_text)
+        };
+        write!(
+            f,
+            "{} <{}>",
+            text(&self.name, "{name}"),
+            text(&self.email, "{email}")
+        )
+    }
+}
+
+impl Serialize for IdentityTemplate {
+    /// `{"name": <template>, "email": <template>}`. A field which is kept is `null`.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("IdentityTemplate", 2)?;
+        state.serialize_field("name", &self.name.as_deref().map(field_text))?;
+        state.serialize_field("email", &self.email.as_deref().map(field_text))?;
+        state.end()
+    }
+}
+
+impl IdentityTemplate {
+    /// Parse the templates of the name and the email. A field is kept if it is `None`.
+    pub fn new(name: Option<&str>, email: Option<&str>) -> Result<Self, TemplateError> {
+        if name.is_none() && email.is_none() {
+            return Err(TemplateError::Empty);
+        }
+        Ok(IdentityTemplate {
+            name: name.map(parse_field).transpose()?,
+            email: email.map(parse_field).transpose()?,
+        })
+    }
+
+    /// Whether the name is replaced.
+    pub fn replaces_name(&self) -> bool {
+        self.name.is_some()
+    }
+
+    /// Whether the email is replaced.
+    pub fn replaces_email(&self) -> bool {
+        self.email.is_some()
+    }
+
+    /// The new author of `old`.
+    pub fn apply(&self, old: &Author) -> Author {
+        let old_name = old.name().as_deref().unwrap_or_default();
+        let old_email = old.email().as_deref().unwrap_or_default();
+        let expand = |parts: &Option<Vec<Part>>, kept: &str| match parts {
+            Some(parts) => parts
+                .iter()
+                .map(|part| match part {
+                    Part::Text(text) => text.as_str(),
+                    Part::Name => old_name,
+                    Part::Email => old_email,
+                    Part::Localpart => local_part(old_email),
+                    Part::Domain => domain(old_email),
+                })
+                .collect(),
+            None => kept.to_string(),
+        };
+        Author::from_raw(
+            &expand(&self.name, old_name),
+            &expand(&self.email, old_email),
+        )
+    }
+
+    /// Statements of the env-filter script which replace the fields.
+    /// `prefix` is `GIT_AUTHOR` or `GIT_COMMITTER`.
+    pub(crate) fn to_shell_text(&self, prefix: &str) -> String {
+        let expand = |parts: &[Part]| -> String {
+            parts
+                .iter()
+                .map(|part| match part {
+                    Part::Text(text) => quote_shell(text),
+                    Part::Name => format!(r#""${}_NAME""#, prefix),
+                    Part::Email => format!(r#""${}_EMAIL""#, prefix),
+                    Part::Localpart => format!(r#""${{{}_EMAIL%@*}}""#, prefix),
+                    Part::Domain => format!(
+                        r#""$(printf '%s' "${}_EMAIL" | sed -n 's/.*@//p')""#,
+                        prefix
+                    ),
+                })
+                .collect()
+        };
+        // Both fields are expanded before either is assigned so that they see the old values.
+        let fields = [("NAME", &self.name), ("EMAIL", &self.email)];
+        let mut assignments = vec![];
+        let mut exports = vec![];
+        for (field, parts) in fields.iter() {
+            if let Some(parts) = parts {
+                let value = if parts.is_empty() {
+                    "''".to_string()
+                } else {
+                    expand(parts)
+                };
+                assignments.push(format!("new_{}={};", field.to_lowercase(), value));
+                exports.push(format!(
+                    r#"{}_{}="$new_{}";"#,
+                    prefix,
+                    field,
+                    field.to_lowercase()
+                ));
+            }
+        }
+        assignments.extend(exports);
+        assignments.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_placeholders() {
+        let template =
+            IdentityTemplate::new(Some("{name} ({{x}})"), Some("{localpart}@new.com")).unwrap();
+        let old = Author::from_raw("Foo", "foo@old.com");
+        assert_eq!(
+            template.apply(&old),
+            Author::from_raw("Foo ({x})", "foo@new.com")
+        );
+        assert_eq!(template.to_string(), "{name} ({{x}}) <{localpart}@new.com>");
+    }
+
+    #[test]
+    fn reject_invalid_templates() {
+        assert!(matches!(
+            IdentityTemplate::new(Some("{nam}"), None),
+            Err(TemplateError::UnknownPlaceholder(key)) if key == "nam"
+        ));
+        assert!(matches!(
+            IdentityTemplate::new(Some("{name"), None),
+            Err(TemplateError::UnmatchedBrace)
+        ));
+        assert!(matches!(
+            IdentityTemplate::new(None, None),
+            Err(TemplateError::Empty)
+        ));
+    }
+
+    #[test]
+    fn reject_characters_of_ident_lines() {
+        for (name, email, c) in &[
+            (Some("Evil\ndata 3"), None, '\n'),
+            (Some("Evil <x@y>"), None, '<'),
+            (None, Some("x@y>"), '>'),
+        ] {
+            assert!(matches!(
+                IdentityTemplate::new(*name, *email),
+                Err(TemplateError::InvalidCharacter(found)) if found == *c
+            ));
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// `[includeIf "gitdir:..."]` section generated by git-author.
+///
+/// ```gitconfig
+/// [includeIf "gitdir:~/work/"]
+///     path = ~/.config/git-author/includes/work.gitconfig
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IncludeRule {
+    gitdir: String,
+    profile: String,
+    path: PathBuf,
+}
+
+impl IncludeRule {
+    pub const FILE_EXTENSION: &'static str = "gitconfig";
+
+    /// Directory of the included identity files. `<config dir>/git-author/includes`
+    pub fn default_dir() -> Option<PathBuf> {
+        Some(super::profile::config_dir()?.join("includes"))
+    }
+
+    /// Identity file of `profile` in `dir`.
+    pub fn include_file_path(dir: &Path, profile: &str) -> PathBuf {
+        dir.join(format!("{}.{}", profile, Self::FILE_EXTENSION))
+    }
+
+    pub fn new<S1, S2>(gitdir: S1, profile: S2, dir: &Path) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        let profile = profile.into();
+        let path = Self::include_file_path(dir, &profile);
+        IncludeRule {
+            gitdir: gitdir.into(),
+            profile,
+            path,
+        }
+    }
+
+    /// Create from a `includeIf.<condition>.path` entry.
+    /// Returns `None` if the entry was not created by git-author.
+    pub(crate) fn from_config_entry(key: &str, value: &str, dir: &Path) -> Option<Self> {
+        let gitdir = Self::gitdir_from_key(key)?;
+        let path = Path::new(value);
+        if path.parent()? != dir || path.extension()? != Self::FILE_EXTENSION {
+            return None;
+        }
+        let profile = path.file_stem()?.to_str()?;
+        Some(Self::new(gitdir, profile, dir))
+    }
+
+    /// `includeif.gitdir:~/work/.path` -> `~/work/`
+    pub(crate) fn gitdir_from_key(key: &str) -> Option<&str> {
+        let prefix = "includeif.gitdir:";
+        let suffix = ".path";
+        if key.len() < prefix.len() + suffix.len()
+            || !key[..prefix.len()].eq_ignore_ascii_case(prefix)
+            || !key[key.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+        {
+            return None;
+        }
+        Some(&key[prefix.len()..key.len() - suffix.len()])
+    }
+
+    /// `includeIf.gitdir:<gitdir>.path`
+    pub fn to_key(&self) -> String {
+        format!("includeIf.gitdir:{}.path", self.gitdir)
+    }
+
+    pub fn gitdir(&self) -> &str {
+        &self.gitdir
+    }
+
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    /// Path of the included identity file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl std::fmt::Display for IncludeRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "gitdir:{} -> {}", self.gitdir, self.profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_round_trips() {
+        let dir = Path::new("/config/git-author/includes");
+        let rule = IncludeRule::new("~/work/", "work", dir);
+        assert_eq!(rule.to_key(), "includeIf.gitdir:~/work/.path");
+        assert_eq!(
+            rule.path(),
+            Path::new("/config/git-author/includes/work.gitconfig")
+        );
+        assert_eq!(rule.to_string(), "gitdir:~/work/ -> work");
+
+        // git prints the section and the variable in lowercase.
+        let key = rule.to_key().to_lowercase().replace("~/work/", "~/Work/");
+        assert_eq!(IncludeRule::gitdir_from_key(&key), Some("~/Work/"));
+        assert_eq!(
+            IncludeRule::from_config_entry(
+                "includeif.gitdir:~/work/.path",
+                "/config/git-author/includes/work.gitconfig",
+                dir
+            ),
+            Some(rule)
+        );
+    }
+
+    #[test]
+    fn gitdir_with_dots() {
+        assert_eq!(
+            IncludeRule::gitdir_from_key("includeif.gitdir:~/src/foo.path.git/.path"),
+            Some("~/src/foo.path.git/")
+        );
+    }
+
+    #[test]
+    fn ignore_entries_which_are_not_created_by_git_author() {
+        let dir = Path::new("/config/git-author/includes");
+        for (key, value) in &[
+            // another directory
+            ("includeif.gitdir:~/work/.path", "/home/foo/.gitconfig-work"),
+            // another extension
+            (
+                "includeif.gitdir:~/work/.path",
+                "/config/git-author/includes/work.inc",
+            ),
+            // a sub directory
+            (
+                "includeif.gitdir:~/work/.path",
+                "/config/git-author/includes/sub/work.gitconfig",
+            ),
+            // another condition
+            (
+                "includeif.onbranch:main.path",
+                "/config/git-author/includes/work.gitconfig",
+            ),
+            ("include.path", "/config/git-author/includes/work.gitconfig"),
+        ] {
+            assert_eq!(
+                IncludeRule::from_config_entry(key, value, dir),
+                None,
+                "{}",
+                key
+            );
+        }
+    }
+}
@@ -0,0 +1,421 @@
+use super::{identity_cluster::propose_canonical, Author};
+use serde::Serialize;
+use std::path::Path;
+
+/// A line of `.mailmap`.
+///
+/// ```text
+/// Proper Name <commit@email>
+/// <proper@email> <commit@email>
+/// Proper Name <proper@email> <commit@email>
+/// Proper Name <proper@email> Commit Name <commit@email>
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MailmapEntry {
+    proper_name: Option<String>,
+    proper_email: Option<String>,
+    commit_name: Option<String>,
+    commit_email: String,
+}
+
+impl MailmapEntry {
+    pub fn new<S: Into<String>>(
+        proper_name: Option<S>,
+        proper_email: Option<S>,
+        commit_name: Option<S>,
+        commit_email: S,
+    ) -> Self {
+        MailmapEntry {
+            proper_name: proper_name.map(|s| s.into()),
+            proper_email: proper_email.map(|s| s.into()),
+            commit_name: commit_name.map(|s| s.into()),
+            commit_email: commit_email.into(),
+        }
+    }
+
+    /// Map `commit` to `proper`. `None` if `commit` has no email.
+    pub fn from_authors(proper: &Author, commit: &Author) -> Option<Self> {
+        Some(MailmapEntry {
+            proper_name: proper.name().clone(),
+            proper_email: proper.email().clone(),
+            commit_name: commit.name().clone(),
+            commit_email: commit.email().clone()?,
+        })
+    }
+
+    /// Parse a line. `None` if the line is a comment or is not valid.
+    /// A comment starts with `#` and continues to the end of the line.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let (name1, email1, rest) = parse_name_and_email(line)?;
+        let entry = match parse_name_and_email(rest) {
+            Some((name2, email2, _)) => MailmapEntry::new(name1, Some(email1), name2, email2),
+            None => MailmapEntry::new(name1, None, None, email1),
+        };
+        Some(entry)
+    }
+
+    pub fn proper_name(&self) -> Option<&str> {
+        self.proper_name.as_deref()
+    }
+
+    pub fn proper_email(&self) -> Option<&str> {
+        self.proper_email.as_deref()
+    }
+
+    /// `None` if the entry matches any name.
+    pub fn commit_name(&self) -> Option<&str> {
+        self.commit_name.as_deref()
+    }
+
+    pub fn commit_email(&self) -> &str {
+        &self.commit_email
+    }
+
+    /// Whether the entry applies to `name` and `email`. Compared case-insensitively like git.
+    pub fn matches(&self, name: &str, email: &str) -> bool {
+        self.commit_email.to_lowercase() == email.to_lowercase()
+            && self
+                .commit_name
+                .as_ref()
+                .is_none_or(|n| n.to_lowercase() == name.to_lowercase())
+    }
+}
+
+impl std::fmt::Display for MailmapEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let mut fields = vec![];
+        if let Some(name) = &self.proper_name {
+            fields.push(name.clone());
+        }
+        if let Some(email) = &self.proper_email {
+            fields.push(format!("<{}>", email));
+        }
+        if let Some(name) = &self.commit_name {
+            fields.push(name.clone());
+        }
+        fields.push(format!("<{}>", self.commit_email));
+        write!(f, "{}", fields.join(" "))
+    }
+}
+
+/// `(name, email, rest)` of `Name <email> rest`. The name is `None` if it is empty.
+fn parse_name_and_email(text: &str) -> Option<(Option<&str>, &str, &str)> {
+    let start = text.find('<')?;
+    let end = start + text[start..].find('>')?;
+    let name = text[..start].trim();
+    let name = if name.is_empty() { None } else { Some(name) };
+    Some((name, &text[start + 1..end], &text[end + 1..]))
+}
+
+/// Contents of `.mailmap`. See `gitmailmap(5)`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mailmap {
+    entries: Vec<MailmapEntry>,
+}
+
+impl Mailmap {
+    pub fn new(entries: Vec<MailmapEntry>) -> Self {
+        Mailmap { entries }
+    }
+
+    /// Parse the text of `.mailmap`. Comments and invalid lines are ignored.
+    pub fn parse(text: &str) -> Self {
+        Mailmap {
+            entries: text.lines().filter_map(MailmapEntry::parse).collect(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
+        Ok(Mailmap::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// Append `entry` to the mailmap file unless the file already has it.
+    /// Returns `false` if it is not appended.
+    pub fn append<P: AsRef<Path>>(path: P, entry: &MailmapEntry) -> Result<bool, std::io::Error> {
+        let path = path.as_ref();
+        let mut text = if path.exists() {
+            std::fs::read_to_string(path)?
+        } else {
+            String::new()
+        };
+        if Mailmap::parse(&text).entries.contains(entry) {
+            return Ok(false);
+        }
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text += &format!("{}\n", entry);
+        std::fs::write(path, text)?;
+        Ok(true)
+    }
+
+    pub fn entries(&self) -> &[MailmapEntry] {
+        &self.entries
+    }
+
+    pub fn push(&mut self, entry: MailmapEntry) {
+        self.entries.push(entry);
+    }
+
+    /// The canonical identity of `author`. `None` if no entry matches.
+    ///
+    /// An entry with the commit name takes precedence over one without it,
+    /// and the later one takes precedence among the same kind like git.
+    pub fn resolve(&self, author: &Author) -> Option<Author> {
+        let name = author.name().as_deref().unwrap_or_default();
+        let email = author.email().as_deref().unwrap_or_default();
+        let matched = |with_name: bool| {
+            self.entries
+                .iter()
+                .rev()
+                .find(|e| e.commit_name.is_some() == with_name && e.matches(name, email))
+        };
+        let entry = matched(true).or_else(|| matched(false))?;
+        Some(Author::from_raw(
+            entry.proper_name().unwrap_or(name),
+            entry.proper_email().unwrap_or(email),
+        ))
+    }
+
+    /// Entries which map the identities that are probably the same person to the canonical one.
+    /// The canonical one is chosen like `IdentityCluster::canonical`.
+    ///
+    /// `identities` are the identities in the history and the numbers of their commits.
+    /// Identities with the same email or the same name (ignoring case) are grouped,
+    /// after they are resolved with the current entries.
+    pub fn suggest(&self, identities: &[(Author, usize)]) -> Vec<MailmapEntry> {
+        let resolved: Vec<_> = identities
+            .iter()
+            .map(|(author, _)| self.resolve(author).unwrap_or_else(|| author.clone()))
+            .collect();
+
+        let mut parents: Vec<_> = (0..identities.len()).collect();
+        fn root(parents: &mut [usize], i: usize) -> usize {
+            let mut i = i;
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+
+        // group the identities which share a key with the first identity which has the key
+        let mut first_index = std::collections::HashMap::new();
+        for (i, author) in resolved.iter().enumerate() {
+            let keys = [
+                author.email().as_ref().map(|email| format!("<{}>", email)),
+                author.name().clone(),
+            ];
+            for key in keys.iter().flatten() {
+                let j = *first_index.entry(key.to_lowercase()).or_insert(i);
+                let (root_i, root_j) = (root(&mut parents, i), root(&mut parents, j));
+                parents[root_i] = root_j;
+            }
+        }
+
+        // the members of each group from the one with the most commits
+        let mut groups: std::collections::HashMap<usize, Vec<usize>> =
+            std::collections::HashMap::new();
+        for i in 0..identities.len() {
+            groups.entry(root(&mut parents, i)).or_default().push(i);
+        }
+        let canonical: std::collections::HashMap<_, _> = groups
+            .into_iter()
+            .map(|(group, mut members)| {
+                members.sort_by_key(|i| std::cmp::Reverse(identities[*i].1));
+                (
+                    group,
+                    propose_canonical(members.iter().map(|i| &resolved[*i])),
+                )
+            })
+            .collect();
+
+        identities
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (author, _))| {
+                let proper = &canonical[&root(&mut parents, i)];
+                if self.resolve(author).as_ref().unwrap_or(author) == proper {
+                    return None;
+                }
+                MailmapEntry::from_authors(proper, author)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn author(name: &str, email: &str) -> Author {
+        Author::from_raw(name, email)
+    }
+
+    #[test]
+    fn parse_forms() {
+        assert_eq!(
+            MailmapEntry::parse("Proper Name <commit@email>"),
+            Some(MailmapEntry::new(
+                Some("Proper Name"),
+                None,
+                None,
+                "commit@email"
+            ))
+        );
+        assert_eq!(
+            MailmapEntry::parse("<proper@email> <commit@email>"),
+            Some(MailmapEntry::new(
+                None,
+                Some("proper@email"),
+                None,
+                "commit@email"
+            ))
+        );
+        assert_eq!(
+            MailmapEntry::parse("Proper Name <proper@email> <commit@email>"),
+            Some(MailmapEntry::new(
+                Some("Proper Name"),
+                Some("proper@email"),
+                None,
+                "commit@email"
+            ))
+        );
+        assert_eq!(
+            MailmapEntry::parse("Proper Name <proper@email> Commit Name <commit@email>"),
+            Some(MailmapEntry::new(
+                Some("Proper Name"),
+                Some("proper@email"),
+                Some("Commit Name"),
+                "commit@email"
+            ))
+        );
+        assert_eq!(MailmapEntry::parse("no email"), None);
+        assert_eq!(MailmapEntry::parse(""), None);
+    }
+
+    #[test]
+    fn parse_comments() {
+        assert_eq!(MailmapEntry::parse("# Proper <a@b.com>"), None);
+        assert_eq!(MailmapEntry::parse("   # Proper <a@b.com>"), None);
+        assert_eq!(
+            MailmapEntry::parse("  Proper <proper@email> <commit@email>  # old laptop"),
+            Some(MailmapEntry::new(
+                Some("Proper"),
+                Some("proper@email"),
+                None,
+                "commit@email"
+            ))
+        );
+        let mailmap = Mailmap::parse("# header\n\tProper <commit@email> # note\n");
+        assert_eq!(
+            mailmap.entries(),
+            &[MailmapEntry::new(
+                Some("Proper"),
+                None,
+                None,
+                "commit@email"
+            )]
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for line in &[
+            "Proper Name <commit@email>",
+            "<proper@email> <commit@email>",
+            "Proper Name <proper@email> Commit Name <commit@email>",
+        ] {
+            assert_eq!(MailmapEntry::parse(line).unwrap().to_string(), *line);
+        }
+    }
+
+    #[test]
+    fn resolve_precedence() {
+        let mailmap = Mailmap::parse(
+            "Email Only <first@example.com> <old@example.com>\n\
+             With Name <named@example.com> Old <old@example.com>\n\
+             Later Email Only <later@example.com> <old@example.com>\n",
+        );
+        // An entry with the commit name takes precedence.
+        assert_eq!(
+            mailmap.resolve(&author("old", "OLD@example.com")),
+            Some(author("With Name", "named@example.com"))
+        );
+        // The later one takes precedence among the entries without the commit name.
+        assert_eq!(
+            mailmap.resolve(&author("Other", "old@example.com")),
+            Some(author("Later Email Only", "later@example.com"))
+        );
+        assert_eq!(mailmap.resolve(&author("Old", "new@example.com")), None);
+    }
+
+    #[test]
+    fn resolve_keeps_missing_fields() {
+        let mailmap =
+            Mailmap::parse("Proper <old@example.com>\n<proper@example.com> <x@example.com>");
+        assert_eq!(
+            mailmap.resolve(&author("old", "old@example.com")),
+            Some(author("Proper", "old@example.com"))
+        );
+        assert_eq!(
+            mailmap.resolve(&author("x", "x@example.com")),
+            Some(author("x", "proper@example.com"))
+        );
+    }
+
+    #[test]
+    fn append_skips_existing_entries() {
+        let path = std::env::temp_dir().join(format!("git-author-{}-mailmap", std::process::id()));
+        std::fs::write(&path, "Proper <proper@email> <commit@email> # kept").unwrap();
+        let entry = MailmapEntry::new(Some("Proper"), Some("proper@email"), None, "commit@email");
+        let other = MailmapEntry::new(Some("Other"), None, None, "other@email");
+        let appended = (
+            Mailmap::append(&path, &entry).unwrap(),
+            Mailmap::append(&path, &other).unwrap(),
+            Mailmap::append(&path, &other).unwrap(),
+        );
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(appended, (false, true, false));
+        assert_eq!(
+            text,
+            "Proper <proper@email> <commit@email> # kept\nOther <other@email>\n"
+        );
+    }
+
+    #[test]
+    fn suggest_prefers_email_which_is_not_auto_generated() {
+        let suggested = Mailmap::default().suggest(&[
+            (author("John Doe", "jd@laptop.local"), 10),
+            (author("John Doe", "john@example.com"), 2),
+            (author("john", "john@example.com"), 1),
+        ]);
+        assert_eq!(
+            suggested,
+            vec![
+                MailmapEntry::from_authors(
+                    &author("John Doe", "john@example.com"),
+                    &author("John Doe", "jd@laptop.local")
+                )
+                .unwrap(),
+                MailmapEntry::from_authors(
+                    &author("John Doe", "john@example.com"),
+                    &author("john", "john@example.com")
+                )
+                .unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn suggest_skips_mapped_identities() {
+        let mailmap = Mailmap::parse("Foo Bar <foo@example.com> <foo@host.(none)>");
+        let suggested = mailmap.suggest(&[
+            (author("Foo Bar", "foo@example.com"), 3),
+            (author("foo", "foo@host.(none)"), 5),
+        ]);
+        assert!(suggested.is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+use super::Author;
+use crate::error::ProfileError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// `$XDG_CONFIG_HOME/git-author` or `~/.config/git-author`
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => dirs::home_dir()?.join(".config"),
+    };
+    Some(config_dir.join("git-author"))
+}
+
+/// Named author. e.g. `work`, `personal`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Profile {
+    name: String,
+    author: Author,
+}
+
+impl Profile {
+    pub fn new<S: Into<String>>(name: S, author: Author) -> Self {
+        Profile {
+            name: name.into(),
+            author,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn author(&self) -> &Author {
+        &self.author
+    }
+}
+
+/// One profile in profiles.toml
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileEntry {
+    name: String,
+    email: String,
+}
+
+/// Contents of profiles.toml
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProfileFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, ProfileEntry>,
+}
+
+/// Profiles saved in a file.
+///
+/// The file looks like this.
+/// ```toml
+/// [profiles.work]
+/// name = "foo"
+/// email = "foo@example.com"
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProfileStore {
+    path: PathBuf,
+    profiles: BTreeMap<String, Author>,
+}
+
+impl ProfileStore {
+    /// `$XDG_CONFIG_HOME/git-author/profiles.toml` or `~/.config/git-author/profiles.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("profiles.toml"))
+    }
+
+    /// Load profiles from the default path.
+    pub fn load_default() -> Result<Self, ProfileError> {
+        let path = Self::default_path().ok_or(ProfileError::ConfigDirNotFound)?;
+        Self::load(path)
+    }
+
+    /// Load profiles from `path`. If the file does not exist, the store is empty.
+    pub fn load<P: Into<PathBuf>>(path: P) -> Result<Self, ProfileError> {
+        let path = path.into();
+        let file = if path.exists() {
+            let text = std::fs::read_to_string(&path)?;
+            toml::from_str::<ProfileFile>(&text)?
+        } else {
+            ProfileFile::default()
+        };
+
+        let mut profiles = BTreeMap::new();
+        for (name, entry) in file.profiles {
+            let author = Author::new(Some(entry.name), Some(entry.email))?;
+            profiles.insert(name, author);
+        }
+
+        Ok(ProfileStore { path, profiles })
+    }
+
+    /// Write profiles to the file.
+    pub fn save(&self) -> Result<(), ProfileError> {
+        let mut file = ProfileFile::default();
+        for (name, author) in &self.profiles {
+            if let (Some(author_name), Some(email)) = (author.name(), author.email()) {
+                file.profiles.insert(
+                    name.clone(),
+                    ProfileEntry {
+                        name: author_name.clone(),
+                        email: email.clone(),
+                    },
+                );
+            }
+        }
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, toml::to_string(&file)?)?;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, name: &str) -> Option<Profile> {
+        self.profiles
+            .get(name)
+            .map(|author| Profile::new(name, author.clone()))
+    }
+
+    pub fn profiles(&self) -> impl Iterator<Item = Profile> + '_ {
+        self.profiles
+            .iter()
+            .map(|(name, author)| Profile::new(name.as_str(), author.clone()))
+    }
+
+    /// Add a profile. If `overwrite` is false, an existing profile is not replaced.
+    pub fn add(&mut self, profile: Profile, overwrite: bool) -> Result<(), ProfileError> {
+        let name = profile.name.trim();
+        if name.is_empty() || name.contains(['/', '\\']) {
+            return Err(ProfileError::InvalidName(profile.name));
+        }
+        if profile.author.has_none_field() {
+            return Err(ProfileError::AuthorField(
+                crate::error::AuthorFieldError::new(&profile.author)?,
+            ));
+        }
+        if !overwrite && self.profiles.contains_key(&profile.name) {
+            return Err(ProfileError::AlreadyExists(profile.name));
+        }
+        self.profiles.insert(profile.name, profile.author);
+        Ok(())
+    }
+
+    /// Remove a profile and return it.
+    pub fn remove(&mut self, name: &str) -> Result<Profile, ProfileError> {
+        self.profiles
+            .remove(name)
+            .map(|author| Profile::new(name, author))
+            .ok_or_else(|| ProfileError::NotFound(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AuthorFieldError;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("git-author-{}-{}", std::process::id(), name))
+    }
+
+    fn profile(name: &str, author_name: &str, email: &str) -> Profile {
+        Profile::new(name, Author::from_raw(author_name, email))
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = temp_path("profiles");
+        let path = dir.join("nested").join("profiles.toml");
+        let mut store = ProfileStore::load(&path).unwrap();
+        assert_eq!(store.profiles().count(), 0);
+        store
+            .add(profile("work", "Foo Bar", "foo@work.example.com"), false)
+            .unwrap();
+        store
+            .add(profile("personal", "foo", "foo@example.com"), false)
+            .unwrap();
+        store.save().unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let loaded = ProfileStore::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(text
+            .contains("[profiles.work]\nname = \"Foo Bar\"\nemail = \"foo@work.example.com\"\n"));
+        // The profiles are sorted by the name.
+        assert_eq!(
+            loaded.profiles().collect::<Vec<_>>(),
+            vec![
+                profile("personal", "foo", "foo@example.com"),
+                profile("work", "Foo Bar", "foo@work.example.com"),
+            ]
+        );
+        assert_eq!(loaded.get("work"), store.get("work"));
+        assert_eq!(loaded.get("missing"), None);
+    }
+
+    #[test]
+    fn load_validates_emails() {
+        let path = temp_path("invalid-profiles.toml");
+        std::fs::write(
+            &path,
+            "[profiles.work]\nname = \"foo\"\nemail = \"no email\"\n",
+        )
+        .unwrap();
+        let result = ProfileStore::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(ProfileError::Addr(_))));
+    }
+
+    #[test]
+    fn add_and_remove() {
+        let mut store = ProfileStore::load(temp_path("unsaved-profiles.toml")).unwrap();
+        store
+            .add(profile("work", "foo", "foo@example.com"), false)
+            .unwrap();
+        assert!(matches!(
+            store.add(profile("work", "bar", "bar@example.com"), false),
+            Err(ProfileError::AlreadyExists(name)) if name == "work"
+        ));
+        store
+            .add(profile("work", "bar", "bar@example.com"), true)
+            .unwrap();
+        assert_eq!(
+            store.get("work"),
+            Some(profile("work", "bar", "bar@example.com"))
+        );
+
+        for name in &["", "  ", "a/b", "a\\b"] {
+            assert!(matches!(
+                store.add(profile(name, "foo", "foo@example.com"), false),
+                Err(ProfileError::InvalidName(_))
+            ));
+        }
+        assert!(matches!(
+            store.add(profile("name-only", "foo", ""), false),
+            Err(ProfileError::AuthorField(AuthorFieldError::EmailIsNone))
+        ));
+
+        assert_eq!(
+            store.remove("work").unwrap(),
+            profile("work", "bar", "bar@example.com")
+        );
+        assert!(matches!(
+            store.remove("work"),
+            Err(ProfileError::NotFound(name)) if name == "work"
+        ));
+    }
+}
@@ -1,98 +1,262 @@
-use super::Author;
-use crate::error::{
-    AuthorFieldError, AuthorHasNoneField, CommitterHasNoneField, ConditionTextError,
-};
+use super::{quote_shell, time_to_text, Commit, IdentityMatcher};
+use crate::error::{ConditionTextError, FilterParseError};
+use regex::Regex;
+use serde::Serialize;
 
-#[derive(Debug)]
+/// Which commits are replaced.
+///
+/// Filters can be combined with `and`, `or` and `!`,
+/// or parsed from an expression like `(author=A or author=B) and not committer=bot`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ReplaceFilter {
-    AuthorOnly(Author),
-    CommitterOnly(Author),
-    AuthorOrCommitter { author: Author, committer: Author },
-    AuthorAndCommitter { author: Author, committer: Author },
+    AuthorOnly(IdentityMatcher),
+    CommitterOnly(IdentityMatcher),
+    AuthorOrCommitter {
+        author: IdentityMatcher,
+        committer: IdentityMatcher,
+    },
+    AuthorAndCommitter {
+        author: IdentityMatcher,
+        committer: IdentityMatcher,
+    },
+    /// all of the filters match
+    And(Vec<ReplaceFilter>),
+    /// any of the filters matches
+    Or(Vec<ReplaceFilter>),
+    /// the filter does not match
+    Not(Box<ReplaceFilter>),
+    /// the author date is in the range. Seconds since the unix epoch. `None` is unbounded.
+    AuthorDate {
+        since: Option<i64>,
+        until: Option<i64>,
+    },
+    /// the committer date is in the range. Seconds since the unix epoch. `None` is unbounded.
+    CommitterDate {
+        since: Option<i64>,
+        until: Option<i64>,
+    },
+    /// the commit message matches the regex
+    Message(#[serde(serialize_with = "super::serialize_regex")] Regex),
+    /// the commit changes a file in the pathspec. The pathspec is relative to the top-level directory.
+    Path(String),
 }
 
-macro_rules! author_condition_format {
-    ($name:expr, $email:expr) => {
-        format!(
-            r#""$GIT_AUTHOR_NAME" = "{}" -a "$GIT_AUTHOR_EMAIL" = "{}""#,
-            $name, $email
-        )
-    };
+const AUTHOR_PREFIX: &str = "GIT_AUTHOR";
+const COMMITTER_PREFIX: &str = "GIT_COMMITTER";
+
+impl std::str::FromStr for ReplaceFilter {
+    type Err = FilterParseError;
+
+    /// Parse a filter expression. See `replace detail --where`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        super::filter_expression::parse(text)
+    }
+}
+
+impl std::ops::Not for ReplaceFilter {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+}
+
+impl std::fmt::Display for ReplaceFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let join = |filters: &[ReplaceFilter], separator: &str| {
+            filters
+                .iter()
+                .map(|filter| format!("({})", filter))
+                .collect::<Vec<_>>()
+                .join(separator)
+        };
+        match self {
+            Self::AuthorOnly(author) => write!(f, "author {}", author),
+            Self::CommitterOnly(committer) => write!(f, "committer {}", committer),
+            Self::AuthorOrCommitter { author, committer } => {
+                write!(f, "author {} or committer {}", author, committer)
+            }
+            Self::AuthorAndCommitter { author, committer } => {
+                write!(f, "author {} and committer {}", author, committer)
+            }
+            Self::And(filters) => write!(f, "{}", join(filters, " and ")),
+            Self::Or(filters) => write!(f, "{}", join(filters, " or ")),
+            Self::Not(filter) => write!(f, "not ({})", filter),
+            Self::AuthorDate { since, until } => {
+                write!(f, "author date{}", date_range_text(*since, *until))
+            }
+            Self::CommitterDate { since, until } => {
+                write!(f, "committer date{}", date_range_text(*since, *until))
+            }
+            Self::Message(regex) => write!(f, "message matches `{}`", regex),
+            Self::Path(pathspec) => write!(f, "path `{}`", pathspec),
+        }
+    }
+}
+
+fn date_range_text(since: Option<i64>, until: Option<i64>) -> String {
+    let since = since.map(|t| format!(" since {}", time_to_text(t)));
+    let until = until.map(|t| format!(" until {}", time_to_text(t)));
+    format!("{}{}", since.unwrap_or_default(), until.unwrap_or_default())
 }
 
-macro_rules! committer_condition_format {
-    ($name:expr, $email:expr) => {
-        format!(
-            r#""$GIT_COMMITTER_NAME" = "{}" -a "$GIT_COMMITTER_EMAIL" = "{}""#,
-            $name, $email
-        )
-    };
+fn in_range(time: i64, since: Option<i64>, until: Option<i64>) -> bool {
+    since.is_none_or(|since| since <= time) && until.is_none_or(|until| time <= until)
 }
 
 impl ReplaceFilter {
-    pub fn to_condition_text(&self) -> Result<String, ConditionTextError> {
+    pub fn author<M: Into<IdentityMatcher>>(matcher: M) -> Self {
+        Self::AuthorOnly(matcher.into())
+    }
+
+    pub fn committer<M: Into<IdentityMatcher>>(matcher: M) -> Self {
+        Self::CommitterOnly(matcher.into())
+    }
+
+    /// Both `self` and `other` match.
+    pub fn and(self, other: ReplaceFilter) -> Self {
         match self {
-            Self::AuthorOnly(author) => match (author.name(), author.email()) {
-                (Some(name), Some(email)) => Ok(author_condition_format!(name, email)),
-                (_, _) => Err(AuthorHasNoneField(AuthorFieldError::new(author)?).into()),
-            },
+            Self::And(mut filters) => {
+                filters.push(other);
+                Self::And(filters)
+            }
+            filter => Self::And(vec![filter, other]),
+        }
+    }
 
-            Self::CommitterOnly(committer) => match (committer.name(), committer.email()) {
-                (Some(name), Some(email)) => Ok(committer_condition_format!(name, email)),
-                (_, _) => Err(CommitterHasNoneField(AuthorFieldError::new(committer)?).into()),
-            },
+    /// Either `self` or `other` matches.
+    pub fn or(self, other: ReplaceFilter) -> Self {
+        match self {
+            Self::Or(mut filters) => {
+                filters.push(other);
+                Self::Or(filters)
+            }
+            filter => Self::Or(vec![filter, other]),
+        }
+    }
 
+    /// Whether the commit matches the filter.
+    /// The same condition as `to_condition_text`.
+    pub fn matches(&self, commit: &Commit) -> bool {
+        match self {
+            Self::AuthorOnly(author) => author.matches(commit.author()),
+            Self::CommitterOnly(committer) => committer.matches(commit.committer()),
             Self::AuthorOrCommitter { author, committer } => {
-                if author.has_none_field() {
-                    Err(AuthorHasNoneField(AuthorFieldError::new(author)?).into())
-                } else if committer.has_none_field() {
-                    Err(CommitterHasNoneField(AuthorFieldError::new(committer)?).into())
-                } else if let (
-                    Some(author_name),
-                    Some(author_email),
-                    Some(committer_name),
-                    Some(committer_email),
-                ) = (
-                    author.name(),
-                    author.email(),
-                    committer.name(),
-                    committer.email(),
-                ) {
-                    Ok(format!(
-                        "{} -o {}",
-                        author_condition_format!(author_name, author_email),
-                        committer_condition_format!(committer_name, committer_email)
-                    ))
-                } else {
-                    unreachable!()
-                }
+                author.matches(commit.author()) || committer.matches(commit.committer())
             }
-
             Self::AuthorAndCommitter { author, committer } => {
-                if author.has_none_field() {
-                    Err(AuthorHasNoneField(AuthorFieldError::new(author)?).into())
-                } else if committer.has_none_field() {
-                    Err(CommitterHasNoneField(AuthorFieldError::new(committer)?).into())
-                } else if let (
-                    Some(author_name),
-                    Some(author_email),
-                    Some(committer_name),
-                    Some(committer_email),
-                ) = (
-                    author.name(),
-                    author.email(),
-                    committer.name(),
-                    committer.email(),
-                ) {
-                    Ok(format!(
-                        "{} -a {}",
-                        author_condition_format!(author_name, author_email),
-                        committer_condition_format!(committer_name, committer_email)
-                    ))
-                } else {
-                    unreachable!()
+                author.matches(commit.author()) && committer.matches(commit.committer())
+            }
+            Self::And(filters) => filters.iter().all(|filter| filter.matches(commit)),
+            Self::Or(filters) => filters.iter().any(|filter| filter.matches(commit)),
+            Self::Not(filter) => !filter.matches(commit),
+            Self::AuthorDate { since, until } => in_range(commit.author_time(), *since, *until),
+            Self::CommitterDate { since, until } => {
+                in_range(commit.committer_time(), *since, *until)
+            }
+            Self::Message(regex) => regex.is_match(commit.message()),
+            Self::Path(pathspec) => commit.touches(pathspec),
+        }
+    }
+
+    /// The pathspecs of the `Path` filters in the filter.
+    pub fn pathspecs(&self) -> Vec<&str> {
+        match self {
+            Self::And(filters) | Self::Or(filters) => filters
+                .iter()
+                .flat_map(|filter| filter.pathspecs())
+                .collect(),
+            Self::Not(filter) => filter.pathspecs(),
+            Self::Path(pathspec) => vec![pathspec.as_str()],
+            _ => vec![],
+        }
+    }
+
+    /// The predicates which make the commit match. Empty if the commit does not match.
+    pub fn matched_predicates(&self, commit: &Commit) -> Vec<String> {
+        if !self.matches(commit) {
+            return vec![];
+        }
+        match self {
+            Self::AuthorOrCommitter { author, committer } => {
+                let mut predicates = vec![];
+                if author.matches(commit.author()) {
+                    predicates.push(Self::AuthorOnly(author.clone()).to_string());
                 }
+                if committer.matches(commit.committer()) {
+                    predicates.push(Self::CommitterOnly(committer.clone()).to_string());
+                }
+                predicates
+            }
+            Self::And(filters) | Self::Or(filters) => filters
+                .iter()
+                .flat_map(|filter| filter.matched_predicates(commit))
+                .collect(),
+            _ => vec![self.to_string()],
+        }
+    }
+
+    pub fn to_condition_text(&self) -> Result<String, ConditionTextError> {
+        let join = |filters: &[ReplaceFilter], operator: &str, empty: &str| {
+            if filters.is_empty() {
+                return Ok(empty.to_string());
+            }
+            let conditions = filters
+                .iter()
+                .map(|filter| Ok(format!(r"\( {} \)", filter.to_condition_text()?)))
+                .collect::<Result<Vec<_>, ConditionTextError>>()?;
+            Ok(conditions.join(operator))
+        };
+        match self {
+            Self::AuthorOnly(author) => author.to_condition_text(AUTHOR_PREFIX),
+            Self::CommitterOnly(committer) => committer.to_condition_text(COMMITTER_PREFIX),
+            Self::AuthorOrCommitter { author, committer } => Ok(format!(
+                "{} -o {}",
+                author.to_condition_text(AUTHOR_PREFIX)?,
+                committer.to_condition_text(COMMITTER_PREFIX)?
+            )),
+            Self::AuthorAndCommitter { author, committer } => Ok(format!(
+                "{} -a {}",
+                author.to_condition_text(AUTHOR_PREFIX)?,
+                committer.to_condition_text(COMMITTER_PREFIX)?
+            )),
+            // `And` of nothing is true and `Or` of nothing is false, like `all` and `any`.
+            Self::And(filters) => join(filters, " -a ", "x = x"),
+            Self::Or(filters) => join(filters, " -o ", "x = y"),
+            Self::Not(filter) => Ok(format!(r"! \( {} \)", filter.to_condition_text()?)),
+            Self::AuthorDate { since, until } => {
+                Ok(date_condition_text(AUTHOR_PREFIX, *since, *until))
+            }
+            Self::CommitterDate { since, until } => {
+                Ok(date_condition_text(COMMITTER_PREFIX, *since, *until))
             }
+            Self::Message(_) => Err(ConditionTextError::UnsupportedByFilterBranch(
+                self.to_string(),
+            )),
+            // `git rev-list --no-walk` prints the commit only if it touches the pathspec.
+            Self::Path(pathspec) => Ok(format!(
+                r#""x$(git rev-list --no-walk --full-history "$GIT_COMMIT" -- {})" = "x$GIT_COMMIT""#,
+                quote_shell(pathspec)
+            )),
         }
     }
 }
+
+/// `$GIT_AUTHOR_DATE` of filter-branch is `@<seconds> <offset>`.
+fn date_condition_text(prefix: &str, since: Option<i64>, until: Option<i64>) -> String {
+    let time = format!(
+        r#""$(printf '%s' "${}_DATE" | sed 's/^@//; s/ .*//')""#,
+        prefix
+    );
+    let conditions: Vec<_> = since
+        .map(|since| format!("{} -ge {}", time, since))
+        .into_iter()
+        .chain(until.map(|until| format!("{} -le {}", time, until)))
+        .collect();
+    if conditions.is_empty() {
+        "x = x".to_string()
+    } else {
+        conditions.join(" -a ")
+    }
+}
@@ -0,0 +1,241 @@
+use crate::error::JournalError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A ref which was rewritten by `replace`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefUpdate {
+    name: String,
+    old: String,
+    new: String,
+}
+
+impl RefUpdate {
+    pub fn new<S1, S2, S3>(name: S1, old: S2, new: S3) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+        S3: Into<String>,
+    {
+        RefUpdate {
+            name: name.into(),
+            old: old.into(),
+            new: new.into(),
+        }
+    }
+
+    /// e.g. `refs/heads/main`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// SHA before the rewrite
+    pub fn old_sha(&self) -> &str {
+        &self.old
+    }
+
+    /// SHA after the rewrite
+    pub fn new_sha(&self) -> &str {
+        &self.new
+    }
+}
+
+/// A record of one `replace`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// seconds since the unix epoch
+    time: u64,
+    /// rewritten revisions. e.g. `HEAD`, `origin/main..HEAD`
+    revision: String,
+    #[serde(default)]
+    undone: bool,
+    /// signed tags which were rewritten
+    #[serde(default)]
+    invalidated_tags: Vec<String>,
+    refs: Vec<RefUpdate>,
+}
+
+impl JournalEntry {
+    pub fn new<S: Into<String>>(time: u64, revision: S, refs: Vec<RefUpdate>) -> Self {
+        JournalEntry {
+            time,
+            revision: revision.into(),
+            undone: false,
+            invalidated_tags: vec![],
+            refs,
+        }
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// `time` as `YYYY-MM-DD hh:mm:ss UTC`
+    pub fn time_text(&self) -> String {
+        super::time_to_text(self.time as i64)
+    }
+
+    pub fn revision(&self) -> &str {
+        &self.revision
+    }
+
+    pub fn refs(&self) -> &[RefUpdate] {
+        &self.refs
+    }
+
+    /// Signed tags which were rewritten. Their signatures are no longer valid.
+    pub fn invalidated_tags(&self) -> &[String] {
+        &self.invalidated_tags
+    }
+
+    pub(crate) fn set_invalidated_tags(&mut self, tags: Vec<String>) {
+        self.invalidated_tags = tags;
+    }
+
+    /// Whether the entry was restored by `replace_undo`.
+    pub fn is_undone(&self) -> bool {
+        self.undone
+    }
+}
+
+/// Contents of the journal file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalFile {
+    #[serde(default)]
+    entries: Vec<JournalEntry>,
+}
+
+/// History of `replace` saved in `.git/git-author/replace-journal.toml`.
+#[derive(Debug, Clone)]
+pub struct ReplaceJournal {
+    path: PathBuf,
+    entries: Vec<JournalEntry>,
+}
+
+impl ReplaceJournal {
+    /// `<git dir>/git-author/replace-journal.toml`
+    pub fn path_in(git_dir: &Path) -> PathBuf {
+        git_dir.join("git-author").join("replace-journal.toml")
+    }
+
+    /// Load the journal from `path`. If the file does not exist, the journal is empty.
+    pub fn load<P: Into<PathBuf>>(path: P) -> Result<Self, JournalError> {
+        let path = path.into();
+        let file = if path.exists() {
+            toml::from_str::<JournalFile>(&std::fs::read_to_string(&path)?)?
+        } else {
+            JournalFile::default()
+        };
+        Ok(ReplaceJournal {
+            path,
+            entries: file.entries,
+        })
+    }
+
+    /// Write the journal to the file.
+    pub fn save(&self) -> Result<(), JournalError> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = JournalFile {
+            entries: self.entries.clone(),
+        };
+        std::fs::write(&self.path, toml::to_string(&file)?)?;
+        Ok(())
+    }
+
+    /// Entries from the oldest.
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+
+    pub fn push(&mut self, entry: JournalEntry) {
+        self.entries.push(entry);
+    }
+
+    /// The latest entry which is not undone.
+    pub fn last_undoable(&self) -> Option<&JournalEntry> {
+        self.entries.iter().rev().find(|e| !e.undone)
+    }
+
+    /// Mark the latest entry which is not undone as undone, and return it.
+    pub(crate) fn mark_last_undone(&mut self) -> Option<JournalEntry> {
+        let entry = self.entries.iter_mut().rev().find(|e| !e.undone)?;
+        entry.undone = true;
+        Some(entry.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("git-author-{}-{}", std::process::id(), name))
+    }
+
+    fn entry(time: u64) -> JournalEntry {
+        JournalEntry::new(
+            time,
+            "HEAD",
+            vec![RefUpdate::new("refs/heads/main", "aaaa", "bbbb")],
+        )
+    }
+
+    #[test]
+    fn save_and_load() {
+        let git_dir = temp_path("journal");
+        let path = ReplaceJournal::path_in(&git_dir);
+        let mut journal = ReplaceJournal::load(&path).unwrap();
+        assert!(journal.entries().is_empty());
+
+        let mut signed = entry(2);
+        signed.set_invalidated_tags(vec!["refs/tags/v1".to_string()]);
+        journal.push(entry(1));
+        journal.push(signed);
+        journal.mark_last_undone();
+        journal.save().unwrap();
+
+        let loaded = ReplaceJournal::load(&path).unwrap();
+        std::fs::remove_dir_all(&git_dir).unwrap();
+        assert_eq!(loaded.entries(), journal.entries());
+        assert!(loaded.entries()[1].is_undone());
+        assert_eq!(loaded.entries()[1].invalidated_tags(), ["refs/tags/v1"]);
+    }
+
+    #[test]
+    fn load_entry_without_optional_fields() {
+        let path = temp_path("old-journal.toml");
+        std::fs::write(
+            &path,
+            r#"
+[[entries]]
+time = 1700000000
+revision = "HEAD"
+
+[[entries.refs]]
+name = "refs/heads/main"
+old = "aaaa"
+new = "bbbb"
+"#,
+        )
+        .unwrap();
+        let journal = ReplaceJournal::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(journal.unwrap().entries(), [entry(1700000000)]);
+    }
+
+    #[test]
+    fn undo_from_the_latest() {
+        let mut journal = ReplaceJournal::load(temp_path("unsaved-journal.toml")).unwrap();
+        assert_eq!(journal.last_undoable(), None);
+        journal.push(entry(1));
+        journal.push(entry(2));
+
+        assert_eq!(journal.mark_last_undone().map(|e| e.time()), Some(2));
+        assert_eq!(journal.last_undoable().map(|e| e.time()), Some(1));
+        assert_eq!(journal.mark_last_undone().map(|e| e.time()), Some(1));
+        assert_eq!(journal.mark_last_undone(), None);
+        assert!(journal.entries().iter().all(|e| e.is_undone()));
+    }
+}
@@ -0,0 +1,260 @@
+use super::{Author, ReplaceFilter, ReplaceTarget};
+use crate::error::MapError;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Which identity of the commits a mapping replaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MapScope {
+    Author,
+    Committer,
+    /// both the author and the committer
+    #[default]
+    Both,
+}
+
+impl std::fmt::Display for MapScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        use MapScope::*;
+        match &self {
+            Author => write!(f, "author"),
+            Committer => write!(f, "committer"),
+            Both => write!(f, "both"),
+        }
+    }
+}
+
+/// Replace `old` with `new`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReplaceMapEntry {
+    old: Author,
+    new: Author,
+    scope: MapScope,
+}
+
+impl ReplaceMapEntry {
+    pub fn new(old: Author, new: Author, scope: MapScope) -> Self {
+        ReplaceMapEntry { old, new, scope }
+    }
+
+    pub fn old(&self) -> &Author {
+        &self.old
+    }
+
+    pub fn new_author(&self) -> &Author {
+        &self.new
+    }
+
+    pub fn scope(&self) -> MapScope {
+        self.scope
+    }
+
+    /// The author and the committer are replaced independently.
+    pub fn to_rules(&self) -> Vec<(ReplaceFilter, ReplaceTarget)> {
+        let author_rule = (
+            ReplaceFilter::AuthorOnly(self.old.clone().into()),
+            ReplaceTarget::Author {
+                new_author: self.new.clone().into(),
+            },
+        );
+        let committer_rule = (
+            ReplaceFilter::CommitterOnly(self.old.clone().into()),
+            ReplaceTarget::Committer {
+                new_committer: self.new.clone().into(),
+            },
+        );
+        match self.scope {
+            MapScope::Author => vec![author_rule],
+            MapScope::Committer => vec![committer_rule],
+            MapScope::Both => vec![author_rule, committer_rule],
+        }
+    }
+}
+
+/// A row of the mapping file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct MapRow {
+    old_name: String,
+    old_email: String,
+    new_name: String,
+    new_email: String,
+    scope: Option<MapScope>,
+}
+
+/// Contents of the TOML mapping file
+#[derive(Debug, Serialize, Deserialize)]
+struct MapFile {
+    #[serde(default)]
+    mapping: Vec<MapRow>,
+}
+
+/// Old and new identities read from a TOML or CSV file.
+///
+/// TOML
+/// ```toml
+/// [[mapping]]
+/// old-name = "jdoe"
+/// old-email = "jdoe@old.example.com"
+/// new-name = "Jane Doe"
+/// new-email = "jane@example.com"
+/// scope = "author" # "author", "committer" or "both". The default is "both".
+/// ```
+///
+/// CSV
+/// ```csv
+/// old-name,old-email,new-name,new-email,scope
+/// jdoe,jdoe@old.example.com,Jane Doe,jane@example.com,author
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplaceMap {
+    entries: Vec<ReplaceMapEntry>,
+}
+
+impl ReplaceMap {
+    pub fn new(entries: Vec<ReplaceMapEntry>) -> Self {
+        ReplaceMap { entries }
+    }
+
+    /// Load a mapping file. The file is read as CSV if the extension is `csv`, otherwise as TOML.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MapError> {
+        let path = path.as_ref();
+        let rows = if path.extension().is_some_and(|ext| ext == "csv") {
+            csv::Reader::from_path(path)?
+                .deserialize()
+                .collect::<Result<Vec<MapRow>, _>>()?
+        } else {
+            toml::from_str::<MapFile>(&std::fs::read_to_string(path)?)?.mapping
+        };
+
+        let entries =
+            rows.into_iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    // The old identity is taken from the history as it is, e.g. `john@vm.(none)`.
+                    let new = Author::new(Some(&row.new_name), Some(&row.new_email)).map_err(
+                        |source| MapError::InvalidEntry {
+                            index: i + 1,
+                            source,
+                        },
+                    )?;
+                    Ok(ReplaceMapEntry::new(
+                        Author::from_raw(&row.old_name, &row.old_email),
+                        new,
+                        row.scope.unwrap_or_default(),
+                    ))
+                })
+                .collect::<Result<_, MapError>>()?;
+        Ok(ReplaceMap { entries })
+    }
+
+    pub fn entries(&self) -> &[ReplaceMapEntry] {
+        &self.entries
+    }
+
+    /// The TOML mapping file which `load` reads. Fields which are `None` are written as empty.
+    pub fn to_toml(&self) -> Result<String, MapError> {
+        let text = |field: &Option<String>| field.clone().unwrap_or_default();
+        let mapping = self
+            .entries
+            .iter()
+            .map(|entry| MapRow {
+                old_name: text(entry.old.name()),
+                old_email: text(entry.old.email()),
+                new_name: text(entry.new.name()),
+                new_email: text(entry.new.email()),
+                scope: Some(entry.scope),
+            })
+            .collect();
+        Ok(toml::to_string(&MapFile { mapping })?)
+    }
+
+    /// Rules for `replace_rules` in the order of the entries.
+    pub fn to_rules(&self) -> Vec<(ReplaceFilter, ReplaceTarget)> {
+        self.entries.iter().flat_map(|e| e.to_rules()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `text` to a file in the temporary directory and load it.
+    fn load(file_name: &str, text: &str) -> Result<ReplaceMap, MapError> {
+        let path =
+            std::env::temp_dir().join(format!("git-author-{}-{}", std::process::id(), file_name));
+        std::fs::write(&path, text).unwrap();
+        let map = ReplaceMap::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        map
+    }
+
+    #[test]
+    fn load_old_email_which_is_not_valid() {
+        let map = load(
+            "invalid-old.toml",
+            r#"
+[[mapping]]
+old-name = "john"
+old-email = "john@vm.(none)"
+new-name = "John Doe"
+new-email = "john@example.com"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            map.entries(),
+            &[ReplaceMapEntry::new(
+                Author::from_raw("john", "john@vm.(none)"),
+                Author::from_raw("John Doe", "john@example.com"),
+                MapScope::Both,
+            )]
+        );
+    }
+
+    #[test]
+    fn load_csv() {
+        let map = load(
+            "map.csv",
+            "old-name,old-email,new-name,new-email,scope\n\
+             jdoe,jdoe@laptop.local,Jane Doe,jane@example.com,author\n",
+        )
+        .unwrap();
+        assert_eq!(map.entries().len(), 1);
+        assert_eq!(map.entries()[0].scope(), MapScope::Author);
+        assert_eq!(
+            map.entries()[0].old(),
+            &Author::from_raw("jdoe", "jdoe@laptop.local")
+        );
+    }
+
+    #[test]
+    fn reject_invalid_new_email() {
+        let error = load(
+            "invalid-new.toml",
+            r#"
+[[mapping]]
+old-name = "john"
+old-email = "john@example.com"
+new-name = "John Doe"
+new-email = "john"
+"#,
+        )
+        .unwrap_err();
+        assert!(matches!(error, MapError::InvalidEntry { index: 1, .. }));
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let map = ReplaceMap::new(vec![ReplaceMapEntry::new(
+            Author::from_raw("john", "john@vm.(none)"),
+            Author::from_raw("John Doe", "john@example.com"),
+            MapScope::Committer,
+        )]);
+        assert_eq!(
+            load("round-trip.toml", &map.to_toml().unwrap()).unwrap(),
+            map
+        );
+    }
+}
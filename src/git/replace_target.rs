@@ -1,79 +1,43 @@
-use super::Author;
-use crate::error::{
-    AuthorFieldError, AuthorHasNoneField, CommitterHasNoneField, ProccessingContentError,
-};
+use super::IdentityTemplate;
+use serde::Serialize;
 
-#[derive(Debug)]
+/// What `replace` replaces. The templates may keep the name or the email, or derive them from the old ones.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ReplaceTarget {
     Author {
-        new_author: Author,
+        new_author: IdentityTemplate,
     },
     Committer {
-        new_committer: Author,
+        new_committer: IdentityTemplate,
     },
     AuthorAndCommitter {
-        new_author: Author,
-        new_committer: Author,
+        new_author: IdentityTemplate,
+        new_committer: IdentityTemplate,
     },
 }
 
-macro_rules! author_format {
-    ($name: expr, $email: expr) => {
-        format!("GIT_AUTHOR_NAME={} GIT_AUTHOR_EMAIL={};", $name, $email)
-    };
-}
-macro_rules! committer_format {
-    ($name: expr, $email: expr) => {
-        format!(
-            "GIT_COMMITTER_NAME={} GIT_COMMITTER_EMAIL={};",
-            $name, $email
-        )
-    };
-}
-
 impl ReplaceTarget {
-    pub fn to_proccessing_content_text(&self) -> Result<String, ProccessingContentError> {
+    /// New author and new committer. `None` if it is not replaced.
+    pub fn replaced(&self) -> (Option<&IdentityTemplate>, Option<&IdentityTemplate>) {
         match self {
-            Self::Author { new_author } => match (new_author.name(), new_author.email()) {
-                (Some(name), Some(email)) => Ok(author_format!(name, email)),
-                (_, _) => Err(AuthorHasNoneField(AuthorFieldError::new(new_author)?).into()),
-            },
-
-            Self::Committer { new_committer } => {
-                match (new_committer.name(), new_committer.email()) {
-                    (Some(name), Some(email)) => Ok(committer_format!(name, email)),
-                    (_, _) => Err(AuthorHasNoneField(AuthorFieldError::new(new_committer)?).into()),
-                }
-            }
-
+            Self::Author { new_author } => (Some(new_author), None),
+            Self::Committer { new_committer } => (None, Some(new_committer)),
             Self::AuthorAndCommitter {
                 new_author,
                 new_committer,
-            } => {
-                if new_author.has_none_field() {
-                    Err(AuthorHasNoneField(AuthorFieldError::new(new_author)?).into())
-                } else if new_committer.has_none_field() {
-                    Err(CommitterHasNoneField(AuthorFieldError::new(new_committer)?).into())
-                } else if let (
-                    Some(author_name),
-                    Some(author_email),
-                    Some(committer_name),
-                    Some(committer_email),
-                ) = (
-                    new_author.name(),
-                    new_author.email(),
-                    new_committer.name(),
-                    new_committer.email(),
-                ) {
-                    Ok(format!(
-                        "{} {}",
-                        author_format!(author_name, author_email),
-                        committer_format!(committer_name, committer_email)
-                    ))
-                } else {
-                    unreachable!()
-                }
-            }
+            } => (Some(new_author), Some(new_committer)),
         }
     }
+
+    /// Shell assignments of the new values for `git filter-branch --env-filter`.
+    pub fn to_proccessing_content_text(&self) -> String {
+        let (new_author, new_committer) = self.replaced();
+        let contents: Vec<_> = new_author
+            .map(|author| author.to_shell_text("GIT_AUTHOR"))
+            .into_iter()
+            .chain(new_committer.map(|committer| committer.to_shell_text("GIT_COMMITTER")))
+            .collect();
+        contents.join(" ")
+    }
 }
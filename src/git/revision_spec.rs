@@ -0,0 +1,45 @@
+/// Commits which `replace` rewrites.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum RevisionSpec {
+    /// the current branch
+    #[default]
+    Head,
+    /// a revision range. e.g. `origin/main..HEAD`
+    Range(String),
+    /// given branches
+    Branches(Vec<String>),
+    /// all branches and tags
+    All,
+}
+
+impl std::fmt::Display for RevisionSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::Head => write!(f, "HEAD"),
+            Self::Range(range) => write!(f, "{}", range),
+            Self::Branches(branches) => write!(f, "{}", branches.join(" ")),
+            Self::All => write!(f, "--all"),
+        }
+    }
+}
+
+impl RevisionSpec {
+    /// arguments of `git rev-list`
+    pub fn to_args(&self) -> Vec<String> {
+        match self {
+            Self::Head => vec!["HEAD".to_string()],
+            Self::Range(range) => vec![range.clone()],
+            Self::Branches(branches) => branches.clone(),
+            // `--all` includes `refs/original/*` which `git filter-branch` leaves.
+            Self::All => vec!["--branches".to_string(), "--tags".to_string()],
+        }
+    }
+
+    /// arguments of `git rev-list` without `--tags`, for when the tags are given separately
+    pub fn to_branch_args(&self) -> Vec<String> {
+        match self {
+            Self::All => vec!["--branches".to_string()],
+            _ => self.to_args(),
+        }
+    }
+}
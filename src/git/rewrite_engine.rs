@@ -0,0 +1,23 @@
+/// How `replace` rewrites the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RewriteEngine {
+    /// `git fast-export | git fast-import`. Fast even on large repositories.
+    #[default]
+    FastExport,
+    /// `git filter-branch --env-filter`. Slow, and deprecated by git.
+    FilterBranch,
+}
+
+impl std::fmt::Display for RewriteEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        use RewriteEngine::*;
+        match &self {
+            FastExport => write!(f, "fast-export"),
+            FilterBranch => write!(f, "filter-branch"),
+        }
+    }
+}
+
+impl RewriteEngine {
+    pub const VARIANTS: [Self; 2] = [Self::FastExport, Self::FilterBranch];
+}
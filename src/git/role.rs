@@ -0,0 +1,29 @@
+use serde::Serialize;
+
+/// Which identity the config values are for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// `user.name` and `user.email`. Used for both author and committer.
+    #[default]
+    User,
+    /// `author.name` and `author.email`. Takes precedence over `user.*` for the author.
+    Author,
+    /// `committer.name` and `committer.email`. Takes precedence over `user.*` for the committer.
+    Committer,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        use Role::*;
+        match &self {
+            User => write!(f, "user"),
+            Author => write!(f, "author"),
+            Committer => write!(f, "committer"),
+        }
+    }
+}
+
+impl Role {
+    pub const VARIANTS: [Self; 3] = [Self::User, Self::Author, Self::Committer];
+}
@@ -0,0 +1,202 @@
+use super::Author;
+use serde::Serialize;
+
+/// A tag which points at a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Tag {
+    name: String,
+    sha: String,
+    target: String,
+    tagger: Option<Author>,
+    tagger_time: i64,
+    subject: String,
+    message: String,
+    signed: bool,
+}
+
+impl Tag {
+    /// `--format` of `git for-each-ref` for `Tag::parse_all`
+    pub(crate) const REF_FORMAT: &'static str = "%(refname)%00%(objecttype)%00%(objectname)%00\
+         %(*objectname)%00%(taggername)%00%(taggeremail:trim)%00%(taggerdate:unix)%00\
+         %(contents:subject)%00%(contents:body)%00%(contents:signature)%00";
+    const FIELD_COUNT: usize = 10;
+
+    /// Parse output of `git for-each-ref --format=<REF_FORMAT> refs/tags`.
+    pub(crate) fn parse_all(output: &str) -> Vec<Self> {
+        let fields: Vec<_> = output.split('\0').collect();
+        fields
+            .chunks_exact(Self::FIELD_COUNT)
+            .map(|f| {
+                let annotated = f[1] == "tag";
+                Tag {
+                    // Each ref but the first starts with `\n` after the `\0` of the previous one.
+                    name: f[0].trim_start_matches('\n').to_string(),
+                    sha: f[2].to_string(),
+                    target: if annotated { f[3] } else { f[2] }.to_string(),
+                    tagger: if annotated {
+                        Some(Author::from_raw(f[4], f[5]))
+                    } else {
+                        None
+                    },
+                    tagger_time: f[6].parse().unwrap_or_default(),
+                    subject: f[7].to_string(),
+                    message: format!("{}\n\n{}", f[7], f[8]),
+                    signed: !f[9].is_empty(),
+                }
+            })
+            .collect()
+    }
+
+    /// e.g. `refs/tags/v1.0.0`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// SHA of the tag object, or of the commit if the tag is lightweight.
+    pub fn sha(&self) -> &str {
+        &self.sha
+    }
+
+    /// SHA of the commit which the tag points at.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// `None` if the tag is lightweight.
+    pub fn tagger(&self) -> Option<&Author> {
+        self.tagger.as_ref()
+    }
+
+    /// Tagger date in seconds since the unix epoch.
+    pub fn tagger_time(&self) -> i64 {
+        self.tagger_time
+    }
+
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// The tag message without the signature.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Whether the tag has a signature, which becomes invalid when the tag is rewritten.
+    pub fn is_signed(&self) -> bool {
+        self.signed
+    }
+}
+
+/// A tag which is rewritten by `replace`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReplacedTag {
+    tag: Tag,
+    new_tagger: Option<Author>,
+    matched_predicates: Vec<String>,
+}
+
+impl ReplacedTag {
+    pub fn new(tag: Tag, new_tagger: Option<Author>, matched_predicates: Vec<String>) -> Self {
+        ReplacedTag {
+            tag,
+            new_tagger,
+            matched_predicates,
+        }
+    }
+
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+
+    /// `None` if the tagger is not replaced.
+    /// The tag is still rewritten when it points at a rewritten commit.
+    pub fn new_tagger(&self) -> Option<&Author> {
+        self.new_tagger.as_ref()
+    }
+
+    /// The predicates of the filters which matched the tagger.
+    pub fn matched_predicates(&self) -> &[String] {
+        &self.matched_predicates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The output of `git for-each-ref` for a ref. Each ref ends with `\0` and `\n`.
+    fn ref_output(fields: [&str; 10]) -> String {
+        format!("{}\0\n", fields.join("\0"))
+    }
+
+    #[test]
+    fn parse_annotated_and_lightweight_tags() {
+        let output = [
+            ref_output([
+                "refs/tags/v1",
+                "tag",
+                "1111",
+                "aaaa",
+                "Foo Bar",
+                "foo@example.com",
+                "1700000000",
+                "release 1",
+                "line 1\nline 2\n",
+                "",
+            ]),
+            ref_output([
+                "refs/tags/v2",
+                "tag",
+                "2222",
+                "bbbb",
+                "Foo Bar",
+                "foo@example.com",
+                "1700000001",
+                "release 2",
+                "",
+                "-----BEGIN PGP SIGNATURE-----\nxxx\n-----END PGP SIGNATURE-----\n",
+            ]),
+            ref_output([
+                "refs/tags/light",
+                "commit",
+                "cccc",
+                "",
+                "",
+                "",
+                "",
+                "subject",
+                "",
+                "",
+            ]),
+        ]
+        .concat();
+        let tags = Tag::parse_all(&output);
+        assert_eq!(tags.len(), 3);
+
+        assert_eq!(tags[0].name(), "refs/tags/v1");
+        assert_eq!(tags[0].sha(), "1111");
+        assert_eq!(tags[0].target(), "aaaa");
+        assert_eq!(
+            tags[0].tagger(),
+            Some(&Author::from_raw("Foo Bar", "foo@example.com"))
+        );
+        assert_eq!(tags[0].tagger_time(), 1700000000);
+        assert_eq!(tags[0].subject(), "release 1");
+        assert_eq!(tags[0].message(), "release 1\n\nline 1\nline 2\n");
+        assert!(!tags[0].is_signed());
+
+        assert_eq!(tags[1].name(), "refs/tags/v2");
+        assert!(tags[1].is_signed());
+
+        assert_eq!(tags[2].name(), "refs/tags/light");
+        assert_eq!(tags[2].sha(), "cccc");
+        assert_eq!(tags[2].target(), "cccc");
+        assert_eq!(tags[2].tagger(), None);
+        assert!(!tags[2].is_signed());
+    }
+
+    #[test]
+    fn parse_no_tags() {
+        assert!(Tag::parse_all("").is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use super::Role;
+
 #[derive(Clone, Copy, Debug)]
 pub enum UserParameter {
     Name,
@@ -5,11 +7,11 @@ pub enum UserParameter {
 }
 
 impl UserParameter {
-    pub fn to_arg(self) -> String {
+    pub fn to_arg(self, role: Role) -> String {
         use UserParameter::*;
         match &self {
-            Name => "user.name".to_string(),
-            Email => "user.email".to_string(),
+            Name => format!("{}.name", role),
+            Email => format!("{}.email", role),
         }
     }
 }
@@ -1,12 +1,20 @@
 use clap::{crate_description, crate_version, App, Arg, ArgMatches, SubCommand};
 use git_author::{
     error::*,
-    git::{self, Author, ConfigFileLocation, ReplaceFilter, ReplaceTarget},
+    git::{
+        self, Author, ConfigEntry, ConfigFileLocation, GitContext, IdentityMatcher,
+        IdentityTemplate, ReplaceFilter, ReplaceTarget, RevisionSpec, RewriteEngine, Role,
+    },
 };
 use std::error::Error as _;
 
 const NAME_KEY: &str = "name";
 const EMAIL_KEY: &str = "email";
+const FILE_KEY: &str = "file";
+const ALL_KEY: &str = "all";
+const SHOW_ORIGIN_KEY: &str = "show-origin";
+const ROLE_KEY: &str = "role";
+const DIRECTORY_KEY: &str = "directory";
 
 fn main() -> Result<(), Error> {
     let result = command();
@@ -33,33 +41,71 @@ fn command() -> Result<(), Error> {
     let config_file_location_args: Vec<_> = config_file_location_name_and_helps
         .iter()
         .map(|(name, help)| {
-            Arg::with_name(&name)
-                .help(&help)
+            Arg::with_name(name)
+                .help(help)
                 .required(false)
                 .group("config")
-                .long(&name)
+                .long(name)
                 .display_order(0)
         })
+        .chain(std::iter::once(
+            Arg::with_name(FILE_KEY)
+                .help("use given config file")
+                .required(false)
+                .group("config")
+                .long(FILE_KEY)
+                .value_name("path")
+                .empty_values(false)
+                .display_order(0),
+        ))
         .collect();
 
+    let role_names: Vec<_> = Role::VARIANTS.iter().map(|r| r.to_string()).collect();
+    let role_names: Vec<_> = role_names.iter().map(|r| r.as_str()).collect();
+    let role_arg = Arg::with_name(ROLE_KEY)
+        .long(ROLE_KEY)
+        .takes_value(true)
+        .possible_values(&role_names)
+        .default_value("user")
+        .help(
+            "`user` uses user.name and user.email. \
+             `author` and `committer` use author.* and committer.*, \
+             which take precedence over user.* for the author or the committer.",
+        )
+        .display_order(0);
+
+    let get_args = [
+        Arg::with_name(ALL_KEY)
+            .long(ALL_KEY)
+            .help("show the values of every scope")
+            .display_order(1),
+        Arg::with_name(SHOW_ORIGIN_KEY)
+            .long(SHOW_ORIGIN_KEY)
+            .help("show the scope and the file (and line) where the values are defined")
+            .display_order(2),
+    ];
+
     let get_subcommand = SubCommand::with_name("get")
         .about("get user.name and user.email")
         .usage("git author (get) [FLAGS]")
         .args(&config_file_location_args)
+        .arg(role_arg.clone())
+        .args(&get_args)
         .display_order(0);
 
     let set_subcommand = {
         let author_args = [
             Arg::with_name(NAME_KEY)
-                .requires(EMAIL_KEY)
                 .empty_values(false)
                 .required(true)
+                .help("name, or `\"Name <email>\"` without <email>")
                 .display_order(1),
-            Arg::with_name(EMAIL_KEY).required(true).display_order(2),
+            Arg::with_name(EMAIL_KEY).display_order(2),
         ];
         SubCommand::with_name("set")
             .about("set user.name and user.email")
             .args(&config_file_location_args)
+            .arg(role_arg.clone())
             .args(&author_args)
             .display_order(1)
     };
@@ -68,25 +114,146 @@ fn command() -> Result<(), Error> {
         .about("unset user.name and user.email")
         .usage("git author unset [FLAGS]")
         .args(&config_file_location_args)
+        .arg(role_arg.clone())
         .display_order(2);
 
+    let engine_names: Vec<_> = RewriteEngine::VARIANTS
+        .iter()
+        .map(|e| e.to_string())
+        .collect();
+    let engine_names: Vec<_> = engine_names.iter().map(|e| e.as_str()).collect();
     let replace_subcommand = {
         use replace::*;
 
+        let dry_run = Arg::with_name(DRY_RUN)
+            .long(DRY_RUN)
+            .help("show the commits which will be rewritten without rewriting them")
+            .display_order(10);
+
+        let tagger = Arg::with_name(TAGGER)
+            .long(TAGGER)
+            .help(
+                "replace the taggers of the annotated tags too. \
+                 The tagger is tested as the author and the committer of the tag. \
+                 Not supported by the `filter-branch` engine",
+            )
+            .display_order(10);
+
+        let engine = Arg::with_name(ENGINE)
+            .long(ENGINE)
+            .takes_value(true)
+            .possible_values(&engine_names)
+            .default_value("fast-export")
+            .help(
+                "how to rewrite the history. \
+                 `fast-export` uses `git fast-export` and `git fast-import`. \
+                 `filter-branch` uses `git filter-branch`, which is much slower.",
+            )
+            .display_order(14);
+
+        let revision_args = [
+            Arg::with_name(RANGE)
+                .long(RANGE)
+                .value_name("range")
+                .takes_value(true)
+                .empty_values(false)
+                .group(REVISION_GROUP)
+                .help("rewrite only the commits in the range. e.g. `origin/main..HEAD`")
+                .display_order(11),
+            Arg::with_name(BRANCHES)
+                .long(BRANCHES)
+                .value_name("branch")
+                .multiple(true)
+                .empty_values(false)
+                .group(REVISION_GROUP)
+                .help("rewrite the given branches")
+                .display_order(12),
+            Arg::with_name(ALL)
+                .long(ALL)
+                .group(REVISION_GROUP)
+                .help("rewrite all branches and tags")
+                .display_order(13),
+        ];
+
+        let predicate_args = [
+            Arg::with_name(SINCE)
+                .long(SINCE)
+                .value_name("date")
+                .takes_value(true)
+                .empty_values(false)
+                .help("rewrite only the commits more recent than the date. e.g. `2020-01-01`, `2 weeks ago`")
+                .display_order(15),
+            Arg::with_name(UNTIL)
+                .long(UNTIL)
+                .value_name("date")
+                .takes_value(true)
+                .empty_values(false)
+                .help("rewrite only the commits older than the date")
+                .display_order(16),
+            Arg::with_name(DATE_TYPE)
+                .long(DATE_TYPE)
+                .takes_value(true)
+                .possible_values(&[DATE_TYPE_AUTHOR, DATE_TYPE_COMMITTER])
+                .default_value(DATE_TYPE_AUTHOR)
+                .help("which date `since` and `until` compare")
+                .display_order(17),
+            Arg::with_name(GREP)
+                .long(GREP)
+                .value_name("regex")
+                .takes_value(true)
+                .empty_values(false)
+                .help(
+                    "rewrite only the commits whose message matches the regex. \
+                     Not supported by the `filter-branch` engine",
+                )
+                .display_order(18),
+            Arg::with_name(PATH)
+                .long(PATH)
+                .value_name("pathspec")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .empty_values(false)
+                .help(
+                    "rewrite only the commits which change a file in the pathspec. \
+                     Relative to the top-level directory. Can be specified more than once",
+                )
+                .display_order(19),
+        ];
+
+        let template_args = [
+            Arg::with_name(SET_NAME)
+                .long(SET_NAME)
+                .value_name("template")
+                .takes_value(true)
+                .help(
+                    "the new name. The email is kept unless `set-email` is specified. \
+                     e.g. `{name}`, `Bot ({localpart})`",
+                )
+                .display_order(6),
+            Arg::with_name(SET_EMAIL)
+                .long(SET_EMAIL)
+                .value_name("template")
+                .takes_value(true)
+                .help(
+                    "the new email. The name is kept unless `set-name` is specified. \
+                     e.g. `{localpart}@newcorp.com`",
+                )
+                .display_order(7),
+        ];
+
         let simple_subcommand = {
             use option::simple::*;
 
             let args = [
                 Arg::with_name(OLD_NAME_KEY)
-                    .requires(OLD_EMAIL_KEY)
                     .empty_values(false)
                     .required(true)
                     .display_order(1),
                 Arg::with_name(OLD_EMAIL_KEY)
-                    .required(true)
+                    .required(false)
                     .display_order(2),
                 Arg::with_name(NEW_NAME_KEY)
-                    .requires(NEW_EMAIL_KEY)
                     .empty_values(false)
                     .required(false)
                     .display_order(3),
@@ -95,8 +262,20 @@ fn command() -> Result<(), Error> {
                     .display_order(4),
             ];
 
+            let template_args: Vec<_> = template_args
+                .iter()
+                .map(|arg| arg.clone().conflicts_with(NEW_NAME_KEY))
+                .collect();
+
             SubCommand::with_name(NAME)
                 .args(&args)
+                .args(&template_args)
+                .after_help(&**AFTER_HELP)
+                .arg(dry_run.clone())
+                .arg(tagger.clone())
+                .arg(engine.clone())
+                .args(&revision_args)
+                .args(&predicate_args)
                 .about(&**option::simple::ABOUT)
                 .display_order(1)
         };
@@ -105,18 +284,64 @@ fn command() -> Result<(), Error> {
             use option::detail::*;
             let filter_author = Arg::with_name(FILTER_AUTHOR)
                 .long(FILTER_AUTHOR)
-                .value_names(&[&NAME_KEY, &EMAIL_KEY])
+                .value_name("author")
+                .min_values(1)
+                .max_values(2)
                 .empty_values(false)
-                .required_unless(FILTER_COMMITTER)
+                .required_unless_one(&[FILTER_COMMITTER, FILTER_PATTERN_GROUP, WHERE])
                 .help(&FILTER_AUTHOR_HELP)
                 .display_order(0);
             let filter_committer = Arg::with_name(FILTER_COMMITTER)
                 .long(FILTER_COMMITTER)
-                .value_names(&[&NAME_KEY, &EMAIL_KEY])
+                .value_name("committer")
+                .min_values(1)
+                .max_values(2)
                 .empty_values(false)
-                .required_unless(FILTER_AUTHOR)
+                .required_unless_one(&[FILTER_AUTHOR, FILTER_PATTERN_GROUP, WHERE])
                 .help(&FILTER_COMMITTER_HELP)
                 .display_order(1);
+            let filter_patterns = [
+                (FILTER_NAME, "name", "filter with the name"),
+                (FILTER_EMAIL, "email", "filter with the email"),
+                (
+                    FILTER_EMAIL_IGNORE_CASE,
+                    "email",
+                    "filter with the email ignoring case",
+                ),
+                (
+                    FILTER_EMAIL_DOMAIN,
+                    "domain",
+                    "filter with the domain of the email. e.g. `example.com`",
+                ),
+                (
+                    FILTER_REGEX,
+                    "regex",
+                    "filter with a regex which matches `name <email>`. \
+                     Not supported by the filter-branch engine",
+                ),
+            ]
+            .iter()
+            .enumerate()
+            .map(|(i, (name, value_name, help))| {
+                Arg::with_name(name)
+                    .long(name)
+                    .value_name(value_name)
+                    .takes_value(true)
+                    .empty_values(false)
+                    .group(FILTER_PATTERN_GROUP)
+                    .conflicts_with_all(&[FILTER_AUTHOR, FILTER_COMMITTER, WHERE])
+                    .help(help)
+                    .display_order(20 + i)
+            })
+            .collect::<Vec<_>>();
+            let where_arg = Arg::with_name(WHERE)
+                .long(WHERE)
+                .value_name("expression")
+                .takes_value(true)
+                .empty_values(false)
+                .conflicts_with_all(&[FILTER_AUTHOR, FILTER_COMMITTER])
+                .help(WHERE_HELP)
+                .display_order(30);
             let filter_type = Arg::with_name(FILTER_TYPE)
                 .long(FILTER_TYPE)
                 .takes_value(true)
@@ -127,13 +352,17 @@ fn command() -> Result<(), Error> {
 
             let replace_author = Arg::with_name(AUTHOR)
                 .long(AUTHOR)
-                .value_names(&[&NAME_KEY, &EMAIL_KEY])
+                .value_name("author")
+                .min_values(1)
+                .max_values(2)
                 .empty_values(false)
                 .help(AUTHOR_HELP)
                 .display_order(3);
             let replace_committer = Arg::with_name(COMMITTER)
                 .long(COMMITTER)
-                .value_names(&[&NAME_KEY, &EMAIL_KEY])
+                .value_name("committer")
+                .min_values(1)
+                .max_values(2)
                 .empty_values(false)
                 .help(COMMITTER_HELP)
                 .display_order(4);
@@ -147,19 +376,332 @@ fn command() -> Result<(), Error> {
 
             SubCommand::with_name(NAME)
                 .display_order(2)
+                .after_help(&**AFTER_HELP)
                 .arg(filter_author)
                 .arg(filter_committer)
-                .arg(replace_author)
-                .arg(replace_committer)
+                .args(&filter_patterns)
+                .arg(where_arg)
+                .arg(replace_author.conflicts_with_all(&[SET_NAME, SET_EMAIL]))
+                .arg(replace_committer.conflicts_with_all(&[SET_NAME, SET_EMAIL]))
+                .args(&template_args)
                 .arg(replace_target)
                 .arg(filter_type)
+                .arg(dry_run.clone())
+                .arg(tagger.clone())
+                .arg(engine.clone())
+                .args(&revision_args)
+                .args(&predicate_args)
         };
 
+        let map_subcommand = SubCommand::with_name(option::MAP)
+            .about(
+                "replace the old authors and committers with the new ones \
+                 in the mapping file at once",
+            )
+            .arg(
+                Arg::with_name(option::MAP_FILE)
+                    .help(
+                        "TOML or CSV file which has `old-name`, `old-email`, `new-name`, \
+                         `new-email` and optional `scope` (`author`, `committer` or `both`)",
+                    )
+                    .required(true)
+                    .empty_values(false)
+                    .display_order(0),
+            )
+            .arg(dry_run.clone())
+            .arg(tagger.clone())
+            .arg(engine.clone())
+            .args(&revision_args)
+            .args(&predicate_args)
+            .display_order(3);
+
+        let undo_subcommand = SubCommand::with_name(option::UNDO)
+            .about("restore the branches and tags rewritten by the last replace")
+            .display_order(4);
+
+        let history_subcommand = SubCommand::with_name(option::HISTORY)
+            .about("show the past replacements")
+            .display_order(5);
+
+        let from_mailmap = Arg::with_name(FROM_MAILMAP)
+            .long(FROM_MAILMAP)
+            .value_name("path")
+            .takes_value(true)
+            .min_values(0)
+            .max_values(1)
+            .require_equals(true)
+            .help(
+                "replace the authors and committers with the canonical ones in the mailmap. \
+                 The default path is `.mailmap` in the top-level directory of the working tree",
+            )
+            .display_order(0);
+
         SubCommand::with_name("replace")
             .about(ABOUT)
             .display_order(3)
+            .arg(from_mailmap)
+            .arg(dry_run)
+            .arg(tagger)
+            .arg(engine)
+            .args(&revision_args)
+            .args(&predicate_args)
             .subcommand(simple_subcommand)
             .subcommand(detail_subcommand)
+            .subcommand(map_subcommand)
+            .subcommand(undo_subcommand)
+            .subcommand(history_subcommand)
+    };
+
+    let whoami_subcommand = SubCommand::with_name("whoami")
+        .about(
+            "show the author and committer which git uses for a new commit \
+             and where they come from",
+        )
+        .display_order(7);
+
+    let profile_subcommand = {
+        use profile::option::*;
+
+        let profile_arg = Arg::with_name(PROFILE_KEY)
+            .empty_values(false)
+            .required(true)
+            .display_order(0);
+
+        let add_subcommand = SubCommand::with_name(ADD)
+            .about("add a profile")
+            .arg(profile_arg.clone())
+            .arg(
+                Arg::with_name(NAME_KEY)
+                    .empty_values(false)
+                    .required(true)
+                    .help("name, or `\"Name <email>\"` without <email>")
+                    .display_order(1),
+            )
+            .arg(Arg::with_name(EMAIL_KEY).display_order(2))
+            .arg(
+                Arg::with_name(FORCE)
+                    .long(FORCE)
+                    .help("overwrite the profile if it already exists")
+                    .display_order(3),
+            )
+            .display_order(0);
+
+        let list_subcommand = SubCommand::with_name(LIST)
+            .about("list profiles")
+            .display_order(1);
+
+        let remove_subcommand = SubCommand::with_name(REMOVE)
+            .about("remove a profile")
+            .arg(profile_arg.clone())
+            .display_order(2);
+
+        let show_subcommand = SubCommand::with_name(SHOW)
+            .about("show a profile")
+            .arg(profile_arg)
+            .display_order(3);
+
+        SubCommand::with_name(NAME)
+            .about(&**ABOUT)
+            .display_order(4)
+            .subcommand(add_subcommand)
+            .subcommand(list_subcommand)
+            .subcommand(remove_subcommand)
+            .subcommand(show_subcommand)
+    };
+
+    let use_subcommand = SubCommand::with_name(profile::option::USE)
+        .about("set user.name and user.email from a profile")
+        .usage("git author use [FLAGS] <profile>")
+        .args(&config_file_location_args)
+        .arg(role_arg)
+        .arg(
+            Arg::with_name(profile::option::PROFILE_KEY)
+                .empty_values(false)
+                .required(true)
+                .display_order(1),
+        )
+        .display_order(5);
+
+    let rule_subcommand = {
+        use rule::option::*;
+
+        let gitdir_arg = Arg::with_name(GITDIR)
+            .long(GITDIR)
+            .takes_value(true)
+            .empty_values(false)
+            .required(true)
+            .help(GITDIR_HELP)
+            .display_order(0);
+
+        let add_subcommand = SubCommand::with_name(ADD)
+            .about("use a profile in the repositories under the directory")
+            .arg(gitdir_arg.clone())
+            .arg(
+                Arg::with_name(profile::option::PROFILE_KEY)
+                    .empty_values(false)
+                    .required(true)
+                    .display_order(1),
+            )
+            .display_order(0);
+
+        let list_subcommand = SubCommand::with_name(LIST)
+            .about("list rules")
+            .display_order(1);
+
+        let remove_subcommand = SubCommand::with_name(REMOVE)
+            .about("remove a rule")
+            .arg(gitdir_arg)
+            .display_order(2);
+
+        SubCommand::with_name(NAME)
+            .about(ABOUT)
+            .display_order(6)
+            .subcommand(add_subcommand)
+            .subcommand(list_subcommand)
+            .subcommand(remove_subcommand)
+    };
+
+    let mailmap_subcommand = {
+        use mailmap::option::*;
+
+        let path_arg = Arg::with_name(MAILMAP_PATH)
+            .long(MAILMAP_PATH)
+            .value_name("path")
+            .takes_value(true)
+            .empty_values(false)
+            .help("mailmap file. The default is `.mailmap` in the top-level directory of the working tree")
+            .display_order(10);
+
+        let suggest_subcommand = SubCommand::with_name(SUGGEST)
+            .about(
+                "suggest entries for the authors and committers which are probably the same person",
+            )
+            .arg(
+                Arg::with_name(RANGE)
+                    .help("commits to look at. e.g. `origin/main..HEAD`. The default is `HEAD`")
+                    .empty_values(false)
+                    .display_order(0),
+            )
+            .arg(
+                Arg::with_name(ALL)
+                    .long(ALL)
+                    .conflicts_with(RANGE)
+                    .help("look at the commits of all branches and tags")
+                    .display_order(1),
+            )
+            .arg(
+                Arg::with_name(WRITE)
+                    .long(WRITE)
+                    .help("append the suggested entries to the mailmap file")
+                    .display_order(2),
+            )
+            .arg(path_arg.clone())
+            .display_order(0);
+
+        let add_subcommand = SubCommand::with_name(ADD)
+            .about("append an entry which maps the old author to the new author")
+            .args(&[
+                Arg::with_name(OLD_NAME_KEY)
+                    .empty_values(false)
+                    .required(true)
+                    .display_order(0),
+                Arg::with_name(OLD_EMAIL_KEY)
+                    .required(true)
+                    .display_order(1),
+                Arg::with_name(NEW_NAME_KEY)
+                    .empty_values(false)
+                    .display_order(2),
+                Arg::with_name(NEW_EMAIL_KEY).display_order(3),
+            ])
+            .after_help(AUTHOR_VALUE_HELP)
+            .arg(path_arg)
+            .display_order(1);
+
+        SubCommand::with_name(NAME)
+            .about(ABOUT)
+            .display_order(8)
+            .subcommand(suggest_subcommand)
+            .subcommand(add_subcommand)
+    };
+
+    let stats_subcommand = {
+        use stats::option::*;
+
+        SubCommand::with_name(NAME)
+            .about(ABOUT)
+            .display_order(9)
+            .arg(
+                Arg::with_name(RANGE)
+                    .help("commits to count. e.g. `origin/main..HEAD`. The default is `HEAD`")
+                    .empty_values(false)
+                    .display_order(0),
+            )
+            .arg(
+                Arg::with_name(ALL)
+                    .long(ALL)
+                    .conflicts_with(RANGE)
+                    .help("count the commits of all branches and tags")
+                    .display_order(1),
+            )
+            .arg(
+                Arg::with_name(SORT)
+                    .long(SORT)
+                    .takes_value(true)
+                    .possible_values(&SORT_KEYS)
+                    .default_value(SORT_COMMITS)
+                    .help("sort by the number of commits (most first), name, email or date")
+                    .display_order(2),
+            )
+            .arg(
+                Arg::with_name(REVERSE)
+                    .long(REVERSE)
+                    .help("reverse the order")
+                    .display_order(3),
+            )
+            .arg(
+                Arg::with_name(FILTER)
+                    .long(FILTER)
+                    .value_name("regex")
+                    .takes_value(true)
+                    .empty_values(false)
+                    .help("show only the identities whose `name <email>` matches the regex")
+                    .display_order(4),
+            )
+            .arg(
+                Arg::with_name(MISMATCHED)
+                    .long(MISMATCHED)
+                    .help("show only the identities which have commits whose author and committer are different")
+                    .display_order(5),
+            )
+    };
+
+    let duplicates_subcommand = {
+        use duplicates::option::*;
+
+        SubCommand::with_name(NAME)
+            .about(ABOUT)
+            .display_order(10)
+            .arg(
+                Arg::with_name(RANGE)
+                    .help("commits to look at. e.g. `origin/main..HEAD`. The default is `HEAD`")
+                    .empty_values(false)
+                    .display_order(0),
+            )
+            .arg(
+                Arg::with_name(ALL)
+                    .long(ALL)
+                    .conflicts_with(RANGE)
+                    .help("look at the commits of all branches and tags")
+                    .display_order(1),
+            )
+            .arg(
+                Arg::with_name(EMIT)
+                    .long(EMIT)
+                    .takes_value(true)
+                    .possible_values(&EMIT_FORMATS)
+                    .help("print the clusters as a mapping file for `replace map` or as `.mailmap` entries")
+                    .display_order(2),
+            )
     };
 
     let app = App::new("git-author")
@@ -167,21 +709,61 @@ fn command() -> Result<(), Error> {
         .usage("git-author [SUBCOMMAND] [FLAGS]")
         .about(crate_description!())
         .args(&config_file_location_args)
+        .arg(
+            Arg::with_name(output::option::FORMAT)
+                .long(output::option::FORMAT)
+                .takes_value(true)
+                .possible_values(&output::option::FORMATS)
+                .global(true)
+                .help("output format. `json` and `tsv` are for scripts [default: text]"),
+        )
+        .arg(
+            Arg::with_name(DIRECTORY_KEY)
+                .short("C")
+                .takes_value(true)
+                .value_name("path")
+                .empty_values(false)
+                .global(true)
+                .help(
+                    "run as if git-author was started in <path> instead of the current directory",
+                ),
+        )
         .subcommand(get_subcommand)
         .subcommand(set_subcommand)
         .subcommand(unset_subcommand)
-        .subcommand(replace_subcommand);
+        .subcommand(whoami_subcommand)
+        .subcommand(replace_subcommand)
+        .subcommand(profile_subcommand)
+        .subcommand(use_subcommand)
+        .subcommand(mailmap_subcommand)
+        .subcommand(stats_subcommand)
+        .subcommand(duplicates_subcommand)
+        .subcommand(rule_subcommand);
 
     let matches = app.get_matches();
 
-    if let Some(ref matches) = matches.subcommand_matches("get") {
-        get_author(&matches)?;
-    } else if let Some(ref matches) = matches.subcommand_matches("set") {
-        set_author(&matches)?;
-    } else if let Some(ref matches) = matches.subcommand_matches("unset") {
-        unset_author(&matches)?;
-    } else if let Some(ref matches) = matches.subcommand_matches("replace") {
-        replace::replace(&matches)?;
+    if let Some(matches) = matches.subcommand_matches("get") {
+        get_author(matches)?;
+    } else if let Some(matches) = matches.subcommand_matches("set") {
+        set_author(matches)?;
+    } else if let Some(matches) = matches.subcommand_matches("unset") {
+        unset_author(matches)?;
+    } else if let Some(matches) = matches.subcommand_matches("whoami") {
+        whoami(matches)?;
+    } else if let Some(matches) = matches.subcommand_matches("replace") {
+        replace::replace(matches)?;
+    } else if let Some(matches) = matches.subcommand_matches(profile::option::NAME) {
+        profile::profile(matches)?;
+    } else if let Some(matches) = matches.subcommand_matches(profile::option::USE) {
+        profile::use_profile(matches)?;
+    } else if let Some(matches) = matches.subcommand_matches(rule::option::NAME) {
+        rule::rule(matches)?;
+    } else if let Some(matches) = matches.subcommand_matches(mailmap::option::NAME) {
+        mailmap::mailmap(matches)?;
+    } else if let Some(matches) = matches.subcommand_matches(stats::option::NAME) {
+        stats::stats(matches)?;
+    } else if let Some(matches) = matches.subcommand_matches(duplicates::option::NAME) {
+        duplicates::duplicates(matches)?;
     } else {
         // get
         get_author(&matches)?;
@@ -191,20 +773,135 @@ fn command() -> Result<(), Error> {
 }
 
 fn get_config_file_location(matches: &ArgMatches) -> Option<ConfigFileLocation> {
-    use ConfigFileLocation::*;
-    if matches.is_present(Global.to_string()) {
-        Some(ConfigFileLocation::Global)
-    } else if matches.is_present(Local.to_string()) {
-        Some(ConfigFileLocation::Local)
-    } else {
-        None
+    if let Some(path) = matches.value_of_os(FILE_KEY) {
+        return Some(ConfigFileLocation::File(path.into()));
     }
+    ConfigFileLocation::VARIANTRS
+        .iter()
+        .find(|location| matches.is_present(location.to_string()))
+        .cloned()
+}
+
+/// `-C <path>`
+fn get_git_context(matches: &ArgMatches) -> GitContext {
+    match matches.value_of_os(DIRECTORY_KEY) {
+        Some(path) => GitContext::new(path),
+        None => GitContext::default(),
+    }
+}
+
+fn get_role(matches: &ArgMatches) -> Role {
+    Role::VARIANTS
+        .iter()
+        .find(|role| matches.value_of(ROLE_KEY) == Some(&role.to_string()))
+        .cloned()
+        .unwrap_or_default()
+}
+
+const AUTHOR_VALUE_HELP: &str = "An author is `<name> <email>` or `\"Name <email>\"`.";
+
+/// Authors of the values. Each author is `<name> <email>` as two values or `"Name <email>"` as one.
+fn values_to_authors<'a, I>(values: I) -> Result<Vec<Author>, Error>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut values = values.into_iter();
+    let mut authors = vec![];
+    while let Some(value) = values.next() {
+        // git does not allow `<` in names.
+        let author = if value.contains('<') {
+            value.parse()?
+        } else {
+            let email = values.next().ok_or(AuthorFieldError::EmailIsNone)?;
+            Author::new(Some(value), Some(email))?
+        };
+        authors.push(author);
+    }
+    Ok(authors)
+}
+
+/// The only author of the values. See `values_to_authors`.
+fn values_to_one_author<'a, I>(values: I) -> Result<Author, Error>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut authors = values_to_authors(values)?;
+    match authors.len() {
+        1 => Ok(authors.remove(0)),
+        _ => Err(InvalidArguments(format!("expected one author. {}", AUTHOR_VALUE_HELP)).into()),
+    }
+}
+
+/// The values of the positional arguments which are present in order.
+fn positional_values<'a>(matches: &'a ArgMatches, keys: &[&str]) -> Vec<&'a str> {
+    keys.iter()
+        .filter_map(|key| matches.value_of(key))
+        .collect()
+}
+
+/// `set`, `unset` and `use` for `--format json` and `--format tsv`
+#[derive(serde::Serialize)]
+struct ConfigChange<'a> {
+    /// `None` is all the config files for `unset`
+    location: Option<&'a ConfigFileLocation>,
+    role: Role,
+    /// `None` for `unset`
+    author: Option<&'a Author>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile: Option<&'a str>,
+}
+
+/// A config value of `get --all` and `get --show-origin` for `--format json` and `--format tsv`
+#[derive(serde::Serialize)]
+struct EntryRecord<'a> {
+    key: &'a str,
+    #[serde(flatten)]
+    entry: &'a ConfigEntry,
+    /// whether git uses the value
+    effective: bool,
+}
+
+/// message of `set` and `use`
+fn set_message(location: &ConfigFileLocation, role: Role, author: &Author) -> String {
+    match role {
+        Role::User => format!("set {} author: {}", location, author),
+        _ => format!("set {} {}: {}", location, role, author),
+    }
+}
+
+/// If `extensions.worktreeConfig` is not enabled, ask whether to enable it.
+fn confirm_worktree_config(
+    context: &GitContext,
+    location: Option<&ConfigFileLocation>,
+) -> Result<(), Error> {
+    if location != Some(&ConfigFileLocation::Worktree) || git::is_worktree_config_enabled(context)?
+    {
+        return Ok(());
+    }
+
+    // The prompt goes to stderr so that stdout stays machine-readable with `--format`.
+    eprint!("extensions.worktreeConfig is not enabled. Do you want to enable it? [y/N] ");
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if matches!(answer.trim(), "y" | "Y" | "yes") {
+        git::enable_worktree_config(context)?;
+        eprintln!("enable extensions.worktreeConfig");
+    }
+    Ok(())
 }
 
 /// display author
 fn get_author(matches: &ArgMatches) -> Result<(), Error> {
-    let config_file_location = get_config_file_location(&matches);
-    let author = git::get_author(config_file_location)?;
+    let config_file_location = get_config_file_location(matches);
+    if matches.is_present(ALL_KEY) || matches.is_present(SHOW_ORIGIN_KEY) {
+        return get_author_entries(matches, config_file_location.as_ref());
+    }
+    let context = get_git_context(matches);
+    let author = git::get_author(&context, config_file_location.as_ref(), get_role(matches))?;
+    let format = output::Format::of(matches);
+    if !format.is_text() {
+        return output::print(format, &author);
+    }
     match (author.name(), author.email()) {
         (Some(name), Some(email)) => println!("{} <{}>", name, email),
         (Some(name), None) => println!("{} (email is empty)", name),
@@ -214,30 +911,144 @@ fn get_author(matches: &ArgMatches) -> Result<(), Error> {
     Ok(())
 }
 
+/// display author with the scopes and the origins
+fn get_author_entries(
+    matches: &ArgMatches,
+    config_file_location: Option<&ConfigFileLocation>,
+) -> Result<(), Error> {
+    let role = get_role(matches);
+    let entries = git::get_author_entries(&get_git_context(matches), config_file_location, role)?;
+    let show_origin = matches.is_present(SHOW_ORIGIN_KEY);
+    let format_entry = |entry: &ConfigEntry| {
+        if show_origin {
+            format!(
+                "{:<8} {} ({})",
+                entry.scope(),
+                entry.value(),
+                entry.origin()
+            )
+        } else {
+            format!("{:<8} {}", entry.scope(), entry.value())
+        }
+    };
+
+    let key_and_entries = [
+        (
+            format!("{}.name", role),
+            entries.name(),
+            entries.effective_name(),
+        ),
+        (
+            format!("{}.email", role),
+            entries.email(),
+            entries.effective_email(),
+        ),
+    ];
+    let format = output::Format::of(matches);
+    if !format.is_text() {
+        let mut records = vec![];
+        for (key, values, _) in &key_and_entries {
+            for (i, entry) in values.iter().enumerate() {
+                // git uses the last value.
+                let effective = i + 1 == values.len();
+                if matches.is_present(ALL_KEY) || effective {
+                    records.push(EntryRecord {
+                        key,
+                        entry,
+                        effective,
+                    });
+                }
+            }
+        }
+        return output::print(format, &records);
+    }
+    for (key, values, effective) in &key_and_entries {
+        if matches.is_present(ALL_KEY) {
+            println!("{}", key);
+            if values.is_empty() {
+                println!("    (empty)");
+            }
+            for (i, entry) in values.iter().enumerate() {
+                // git uses the last value.
+                let mark = if i + 1 == values.len() { "*" } else { " " };
+                println!("  {} {}", mark, format_entry(entry));
+            }
+        } else {
+            let width = key_and_entries[1].0.len();
+            match effective {
+                Some(entry) => println!("{:<width$} : {}", key, format_entry(entry), width = width),
+                None => println!("{:<width$} : (empty)", key, width = width),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// display the effective author and committer
+fn whoami(matches: &ArgMatches) -> Result<(), Error> {
+    let identity = git::get_effective_identity(&get_git_context(matches))?;
+    let format = output::Format::of(matches);
+    if !format.is_text() {
+        return output::print(format, &identity);
+    }
+    let sources = &identity.sources;
+    println!("author   : {}", identity.author);
+    println!("    name : {}", sources.author_name);
+    println!("    email: {}", sources.author_email);
+    println!("committer: {}", identity.committer);
+    println!("    name : {}", sources.committer_name);
+    println!("    email: {}", sources.committer_email);
+    Ok(())
+}
+
 fn set_author(matches: &ArgMatches) -> Result<(), Error> {
-    match (matches.value_of(NAME_KEY), matches.value_of(EMAIL_KEY)) {
-        (Some(name), Some(email)) => {
-            let config_file_location =
-                get_config_file_location(&matches).unwrap_or(ConfigFileLocation::Local);
-            let author = Author::new(Some(name), Some(email))?;
-            git::set_author(config_file_location, &author)?;
-            println!("set {} author: {}", config_file_location, author);
-            Ok(())
-        }
-        (None, Some(_)) => Err(AuthorFieldError::NameIsNone.into()),
-        (Some(_), None) => Err(AuthorFieldError::EmailIsNone.into()),
-        (None, None) => Err(AuthorFieldError::NameAndEmailAreNone.into()),
+    let author = values_to_one_author(positional_values(matches, &[NAME_KEY, EMAIL_KEY]))?;
+    let config_file_location =
+        get_config_file_location(matches).unwrap_or(ConfigFileLocation::Local);
+    let context = get_git_context(matches);
+    confirm_worktree_config(&context, Some(&config_file_location))?;
+    let role = get_role(matches);
+    git::set_author(&context, &config_file_location, role, &author)?;
+    let format = output::Format::of(matches);
+    if !format.is_text() {
+        return output::print(
+            format,
+            &ConfigChange {
+                location: Some(&config_file_location),
+                role,
+                author: Some(&author),
+                profile: None,
+            },
+        );
     }
+    println!("{}", set_message(&config_file_location, role, &author));
+    Ok(())
 }
 
 fn unset_author(matches: &ArgMatches) -> Result<(), Error> {
-    let config_file_location = get_config_file_location(&matches);
-    git::unset_author(config_file_location)?;
-    Ok(())
+    let config_file_location = get_config_file_location(matches);
+    let context = get_git_context(matches);
+    confirm_worktree_config(&context, config_file_location.as_ref())?;
+    let role = get_role(matches);
+    git::unset_author(&context, config_file_location.as_ref(), role)?;
+    output::print(
+        output::Format::of(matches),
+        &ConfigChange {
+            location: config_file_location.as_ref(),
+            role,
+            author: None,
+            profile: None,
+        },
+    )
 }
 
 mod replace {
     pub mod option {
+        pub const MAP: &str = "map";
+        pub const MAP_FILE: &str = "file";
+        pub const UNDO: &str = "undo";
+        pub const HISTORY: &str = "history";
+
         pub mod detail {
             use lazy_static::lazy_static;
 
@@ -249,6 +1060,22 @@ mod replace {
             pub const FILTER_AUTHOR_OR_COMMITTER: &str = "or";
             pub const FILTER_AUTHOR_AND_COMMITTER: &str = "and";
 
+            pub const FILTER_PATTERN_GROUP: &str = "filter-pattern";
+            pub const FILTER_NAME: &str = "filter-name";
+            pub const FILTER_EMAIL: &str = "filter-email";
+            pub const FILTER_EMAIL_IGNORE_CASE: &str = "filter-email-ignore-case";
+            pub const FILTER_EMAIL_DOMAIN: &str = "filter-email-domain";
+            pub const FILTER_REGEX: &str = "filter-regex";
+
+            pub const WHERE: &str = "where";
+            pub const WHERE_HELP: &str =
+                "filter with an expression. e.g. `(author=A or author.email=b@example.com) \
+                 and not committer.name=bot`. \
+                 `author`, `author.name`, `author.email`, `author.email-ignore-case`, \
+                 `author.email-domain` and the same for `committer` can be compared \
+                 with `=` and `!=`, and `author` and `committer` with `=~` (regex). \
+                 `path=<pathspec>` matches the commits which change a file in the pathspec.";
+
             pub const REPLACE_TARGET: &str = "replace-target";
             pub const REPLACE_TARGET_AUTHOR: &str = "author";
             pub const REPLACE_TARGET_COMMITTER: &str = "committer";
@@ -265,12 +1092,25 @@ mod replace {
 
             lazy_static! {
                 pub static ref FILTER_AUTHOR_HELP: String = format!(
-                    "filter with author. Required when `{}` is not specified.",
-                    FILTER_COMMITTER
+                    "filter with author. \
+                     Required when neither `{}` nor `{}` and so on is specified.",
+                    FILTER_COMMITTER, FILTER_NAME
                 );
                 pub static ref FILTER_COMMITTER_HELP: String = format!(
-                    "filter with committer. Required when `{}` is not specified.",
-                    FILTER_AUTHOR
+                    "filter with committer. \
+                     Required when neither `{}` nor `{}` and so on is specified.",
+                    FILTER_AUTHOR, FILTER_NAME
+                );
+                pub static ref PATTERN_HELP: String = format!(
+                    "`{}` and so on test the author or the committer which `{}` replaces, \
+                     and each of them is replaced only if it matches.",
+                    FILTER_NAME, REPLACE_TARGET
+                );
+                pub static ref AFTER_HELP: String = format!(
+                    "{}\n{}\n{}",
+                    *PATTERN_HELP,
+                    super::super::TEMPLATE_HELP,
+                    crate::AUTHOR_VALUE_HELP
                 );
                 pub static ref FILTER_TYPE_HELP: String = format!(
                     "You can specify `{and}` or `{or}`. \
@@ -313,10 +1153,39 @@ mod replace {
                     NEW_NAME_KEY,
                     NEW_EMAIL_KEY
                 );
+                pub static ref AFTER_HELP: String = format!(
+                    "{}\n{}",
+                    crate::AUTHOR_VALUE_HELP,
+                    super::super::TEMPLATE_HELP
+                );
             }
         }
     }
 
+    pub const DRY_RUN: &str = "dry-run";
+    pub const TAGGER: &str = "tagger";
+    pub const ENGINE: &str = "engine";
+    pub const FROM_MAILMAP: &str = "from-mailmap";
+
+    pub const REVISION_GROUP: &str = "revision";
+    pub const RANGE: &str = "range";
+    pub const BRANCHES: &str = "branches";
+    pub const ALL: &str = "all";
+
+    pub const SINCE: &str = "since";
+    pub const UNTIL: &str = "until";
+    pub const DATE_TYPE: &str = "date-type";
+    pub const DATE_TYPE_AUTHOR: &str = "author";
+    pub const DATE_TYPE_COMMITTER: &str = "committer";
+    pub const GREP: &str = "grep";
+    pub const PATH: &str = "path";
+
+    pub const SET_NAME: &str = "set-name";
+    pub const SET_EMAIL: &str = "set-email";
+    pub const TEMPLATE_HELP: &str =
+        "`set-name` and `set-email` can have `{name}`, `{email}`, `{localpart}` and `{domain}` \
+         of the old author or committer. Write `{{` and `}}` for literal braces.";
+
     pub const ABOUT: &str = "The author or committer replaces the commit author and committer of \
                              `old author name <old author email>` \
                              with `new author name <new author email>`";
@@ -324,35 +1193,212 @@ mod replace {
     use super::*;
 
     pub fn replace(matches: &ArgMatches) -> Result<(), Error> {
-        if let Some(ref matches) = matches.subcommand_matches(option::simple::NAME) {
-            replace_simple(&matches)?;
-        } else if let Some(ref matches) = matches.subcommand_matches(option::detail::NAME) {
-            replace_detail(&matches)?;
+        check_mailmap_options(matches)?;
+        if let Some(matches) = matches.subcommand_matches(option::simple::NAME) {
+            replace_simple(matches)?;
+        } else if let Some(matches) = matches.subcommand_matches(option::detail::NAME) {
+            replace_detail(matches)?;
+        } else if let Some(matches) = matches.subcommand_matches(option::MAP) {
+            replace_map(matches)?;
+        } else if matches.is_present(FROM_MAILMAP) {
+            replace_from_mailmap(matches)?;
+        } else if let Some(matches) = matches.subcommand_matches(option::UNDO) {
+            undo(matches)?;
+        } else if let Some(matches) = matches.subcommand_matches(option::HISTORY) {
+            history(matches)?;
+        }
+        Ok(())
+    }
+
+    /// The options which `replace` has for `--from-mailmap`.
+    /// The subcommands read only their own options, so they are rejected before a subcommand.
+    /// `occurrences_of` is used because `--engine` has a default value.
+    fn check_mailmap_options(matches: &ArgMatches) -> Result<(), Error> {
+        let options = [
+            DRY_RUN, TAGGER, ENGINE, RANGE, BRANCHES, ALL, SINCE, UNTIL, DATE_TYPE, GREP, PATH,
+        ];
+        let message = match (matches.subcommand_name(), matches.is_present(FROM_MAILMAP)) {
+            (Some(name), true) => format!("`--{}` cannot be used with `{}`", FROM_MAILMAP, name),
+            (Some(name), false) => match options.iter().find(|o| matches.occurrences_of(o) > 0) {
+                Some(option) => format!("`--{}` must be given after `{}`", option, name),
+                None => return Ok(()),
+            },
+            (None, false) => match options.iter().find(|o| matches.occurrences_of(o) > 0) {
+                Some(option) => format!("`--{}` needs `--{}`", option, FROM_MAILMAP),
+                None => return Ok(()),
+            },
+            (None, true) => return Ok(()),
+        };
+        Err(InvalidArguments(message).into())
+    }
+
+    fn replace_map(matches: &ArgMatches) -> Result<(), Error> {
+        let map = match matches.value_of_os(option::MAP_FILE) {
+            Some(path) => git::ReplaceMap::load(path)?,
+            None => return Ok(()),
+        };
+        if output::Format::of(matches).is_text() {
+            for entry in map.entries() {
+                println!(
+                    "{} -> {} ({})",
+                    entry.old(),
+                    entry.new_author(),
+                    entry.scope()
+                );
+            }
+        }
+
+        let rules = restrict_rules(matches, map.to_rules())?;
+        let revision = parse_revision(matches);
+        rewrite(matches, &rules, &revision)?;
+        Ok(())
+    }
+
+    fn replace_from_mailmap(matches: &ArgMatches) -> Result<(), Error> {
+        let context = get_git_context(matches);
+        let path = match matches.value_of_os(FROM_MAILMAP) {
+            Some(path) => path.into(),
+            None => git::default_mailmap_path(&context).ok_or_else(|| {
+                InvalidArguments("`.mailmap` is not found. Specify the path.".to_string())
+            })?,
+        };
+        let mailmap = git::Mailmap::load(&path)?;
+
+        let revision = parse_revision(matches);
+        let map = git::mailmap_to_replace_map(&context, &mailmap, &revision)?;
+        if output::Format::of(matches).is_text() {
+            for entry in map.entries() {
+                println!("{} -> {}", entry.old(), entry.new_author());
+            }
+        }
+
+        let rules = restrict_rules(matches, map.to_rules())?;
+        rewrite(matches, &rules, &revision)?;
+        Ok(())
+    }
+
+    fn undo(matches: &ArgMatches) -> Result<(), Error> {
+        let entry = git::replace_undo(&get_git_context(matches))?;
+        let format = output::Format::of(matches);
+        if !format.is_text() {
+            return output::print(format, &entry);
+        }
+        println!("restored the replacement at {}", entry.time_text());
+        for update in entry.refs() {
+            println!(
+                "    {}: {:.10} -> {:.10}",
+                update.name(),
+                update.new_sha(),
+                update.old_sha()
+            );
+        }
+        Ok(())
+    }
+
+    fn history(matches: &ArgMatches) -> Result<(), Error> {
+        let entries: Vec<_> = git::replace_history(&get_git_context(matches))?
+            .into_iter()
+            .rev()
+            .collect();
+        let format = output::Format::of(matches);
+        if !format.is_text() {
+            return output::print(format, &entries);
+        }
+        for entry in &entries {
+            println!(
+                "{} {}{}",
+                entry.time_text(),
+                entry.revision(),
+                if entry.is_undone() { " (undone)" } else { "" }
+            );
+            for update in entry.refs() {
+                println!(
+                    "    {}: {:.10} -> {:.10}",
+                    update.name(),
+                    update.old_sha(),
+                    update.new_sha()
+                );
+            }
         }
         Ok(())
     }
 
+    fn print_journal_entry(entry: &git::JournalEntry) {
+        if entry.refs().is_empty() {
+            println!("no branches or tags were rewritten");
+            return;
+        }
+        for update in entry.refs() {
+            println!(
+                "rewrote {}: {:.10} -> {:.10}",
+                update.name(),
+                update.old_sha(),
+                update.new_sha()
+            );
+        }
+        for tag in entry.invalidated_tags() {
+            println!("the signature of {} is no longer valid. Sign it again", tag);
+        }
+        println!("run `git author replace undo` to restore them");
+    }
+
     fn replace_detail(matches: &ArgMatches) -> Result<(), Error> {
-        let filter = parse_filter(&matches)?;
-        match &filter {
-            ReplaceFilter::AuthorOnly(author) => println!("filter author: {}", author),
-            ReplaceFilter::CommitterOnly(committer) => println!("filter committer: {}", committer),
-            ReplaceFilter::AuthorOrCommitter { author, committer } => println!(
+        let pattern = parse_pattern(matches)?;
+        let filter = match &pattern {
+            Some(_) => None,
+            None => match matches.value_of(option::detail::WHERE) {
+                Some(expression) => Some(expression.parse()?),
+                None => Some(parse_filter(matches)?),
+            },
+        };
+        let target = parse_target(matches)?;
+        // With `--format json` or `--format tsv`, the rules are printed with the result.
+        if output::Format::of(matches).is_text() {
+            print_filter_and_target(pattern.as_ref(), filter.as_ref(), &target);
+        }
+
+        let rules = match (filter, pattern) {
+            (Some(filter), _) => vec![(filter, target)],
+            (None, Some(matcher)) => pattern_rules(matcher, target),
+            (None, None) => vec![],
+        };
+        let rules = restrict_rules(matches, rules)?;
+        let revision = parse_revision(matches);
+        rewrite(matches, &rules, &revision)?;
+
+        Ok(())
+    }
+
+    fn print_filter_and_target(
+        pattern: Option<&IdentityMatcher>,
+        filter: Option<&ReplaceFilter>,
+        target: &ReplaceTarget,
+    ) {
+        if let Some(matcher) = pattern {
+            println!("filter: {}", matcher);
+        }
+        match filter {
+            Some(ReplaceFilter::AuthorOnly(author)) => println!("filter author: {}", author),
+            Some(ReplaceFilter::CommitterOnly(committer)) => {
+                println!("filter committer: {}", committer)
+            }
+            Some(ReplaceFilter::AuthorOrCommitter { author, committer }) => println!(
                 "filter author or committer\n\
                  author   : {}\n\
                  committer: {}",
                 author, committer
             ),
-            ReplaceFilter::AuthorAndCommitter { author, committer } => println!(
+            Some(ReplaceFilter::AuthorAndCommitter { author, committer }) => println!(
                 "filter author and committer\n\
                  author   : {}\n\
                  committer: {}",
                 author, committer
             ),
+            Some(filter) => println!("filter: {}", filter),
+            None => {}
         };
 
-        let target = parse_target(&matches)?;
-        match &target {
+        match target {
             ReplaceTarget::Author { new_author } => println!("new author: {}", new_author),
             ReplaceTarget::Committer { new_committer } => {
                 println!("new committer: {}", new_committer)
@@ -366,45 +1412,293 @@ mod replace {
                 new_author, new_committer
             ),
         }
+    }
 
-        git::replace(filter, target)?;
+    /// The author and the committer which `target` replaces are tested independently.
+    fn pattern_rules(
+        matcher: IdentityMatcher,
+        target: ReplaceTarget,
+    ) -> Vec<(ReplaceFilter, ReplaceTarget)> {
+        match target {
+            ReplaceTarget::Author { .. } => vec![(ReplaceFilter::AuthorOnly(matcher), target)],
+            ReplaceTarget::Committer { .. } => {
+                vec![(ReplaceFilter::CommitterOnly(matcher), target)]
+            }
+            ReplaceTarget::AuthorAndCommitter {
+                new_author,
+                new_committer,
+            } => vec![
+                (
+                    ReplaceFilter::AuthorOnly(matcher.clone()),
+                    ReplaceTarget::Author { new_author },
+                ),
+                (
+                    ReplaceFilter::CommitterOnly(matcher),
+                    ReplaceTarget::Committer { new_committer },
+                ),
+            ],
+        }
+    }
+
+    #[derive(serde::Serialize)]
+    struct Rule<'a> {
+        filter: &'a ReplaceFilter,
+        target: &'a ReplaceTarget,
+    }
 
+    /// result of `replace`. Printed as is with `--format json` and `--format tsv`
+    #[derive(serde::Serialize)]
+    struct RewriteResult<'a> {
+        rules: Vec<Rule<'a>>,
+        /// commits which will be rewritten with `--dry-run`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        commits: Option<Vec<git::ReplacedCommit>>,
+        /// tags which will be rewritten with `--dry-run`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tags: Option<Vec<git::ReplacedTag>>,
+        /// what was rewritten without `--dry-run`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        journal: Option<git::JournalEntry>,
+    }
+
+    /// Rewrite with the rules, or show what will be rewritten with `--dry-run`.
+    fn rewrite(
+        matches: &ArgMatches,
+        rules: &[(ReplaceFilter, ReplaceTarget)],
+        revision: &RevisionSpec,
+    ) -> Result<(), Error> {
+        let context = get_git_context(matches);
+        let taggers = matches.is_present(TAGGER);
+        let mut result = RewriteResult {
+            rules: rules
+                .iter()
+                .map(|(filter, target)| Rule { filter, target })
+                .collect(),
+            commits: None,
+            tags: None,
+            journal: None,
+        };
+        if matches.is_present(DRY_RUN) {
+            let (commits, tags) =
+                git::replace_rules_dry_run_with_tags(&context, rules, revision, taggers)?;
+            result.commits = Some(commits);
+            result.tags = Some(tags);
+        } else if taggers {
+            result.journal = Some(git::replace_rules_with_taggers(
+                &context,
+                rules,
+                revision,
+                parse_engine(matches),
+            )?);
+        } else {
+            result.journal = Some(git::replace_rules(
+                &context,
+                rules,
+                revision,
+                parse_engine(matches),
+            )?);
+        }
+
+        let format = output::Format::of(matches);
+        if !format.is_text() {
+            return output::print(format, &result);
+        }
+        if let Some(commits) = &result.commits {
+            print_replaced_commits(commits);
+        }
+        if let Some(tags) = &result.tags {
+            print_replaced_tags(tags);
+        }
+        if let Some(journal) = &result.journal {
+            print_journal_entry(journal);
+        }
         Ok(())
     }
 
+    fn print_replaced_tags(replaced_tags: &[git::ReplacedTag]) {
+        for replaced in replaced_tags {
+            let tag = replaced.tag();
+            println!("{} {}", tag.name(), tag.subject());
+            match (tag.tagger(), replaced.new_tagger()) {
+                (Some(old), Some(new)) => println!("    tagger   : {} -> {}", old, new),
+                (Some(old), None) => println!("    tagger   : {} (unchanged)", old),
+                (None, _) => {}
+            }
+            for predicate in replaced.matched_predicates() {
+                println!("    matched  : {}", predicate);
+            }
+            if tag.is_signed() {
+                println!("    the signature will be invalid");
+            }
+        }
+        if !replaced_tags.is_empty() {
+            println!("{} tag(s) will be rewritten", replaced_tags.len());
+        }
+    }
+
+    fn print_replaced_commits(replaced_commits: &[git::ReplacedCommit]) {
+        let print_change = |label: &str, old: &Author, new: Option<&Author>| match new {
+            Some(new) if new != old => println!("    {}: {} -> {}", label, old, new),
+            Some(_) => println!("    {}: {} (same)", label, old),
+            None => println!("    {}: {} (unchanged)", label, old),
+        };
+
+        for replaced in replaced_commits {
+            let commit = replaced.commit();
+            println!("{:.10} {}", commit.sha(), commit.subject());
+            print_change("author   ", commit.author(), replaced.new_author());
+            print_change("committer", commit.committer(), replaced.new_committer());
+            for predicate in replaced.matched_predicates() {
+                println!("    matched  : {}", predicate);
+            }
+        }
+        println!("{} commit(s) will be rewritten", replaced_commits.len());
+    }
+
     fn replace_simple(matches: &ArgMatches) -> Result<(), Error> {
         use option::simple::*;
 
-        let old_name = matches.value_of(OLD_NAME_KEY);
-        let old_email = matches.value_of(OLD_EMAIL_KEY);
-        let old_author = Author::new(old_name, old_email)?;
+        let mut authors = values_to_authors(positional_values(
+            matches,
+            &[OLD_NAME_KEY, OLD_EMAIL_KEY, NEW_NAME_KEY, NEW_EMAIL_KEY],
+        ))?
+        .into_iter();
+        let (old_author, new_author) = match (authors.next(), authors.next(), authors.next()) {
+            (Some(old), new, None) => (old, new),
+            _ => {
+                return Err(InvalidArguments(format!(
+                    "expected the old author and the new author. {}",
+                    AUTHOR_VALUE_HELP
+                ))
+                .into())
+            }
+        };
+
+        let new_author = match (parse_template(matches)?, new_author) {
+            (Some(_), Some(_)) => {
+                return Err(InvalidArguments(
+                    "the new author cannot be used with `--set-name` or `--set-email`".to_string(),
+                )
+                .into())
+            }
+            (Some(template), None) => template,
+            (None, Some(author)) => author.into(),
+            (None, None) => git::get_author(&get_git_context(matches), None, Role::User)?.into(),
+        };
+
+        let revision = parse_revision(matches);
+        let rules = restrict_rules(matches, git::simple_rules(&old_author, &new_author))?;
+        rewrite(matches, &rules, &revision)?;
+        Ok(())
+    }
+
+    fn parse_revision(matches: &ArgMatches) -> RevisionSpec {
+        if let Some(range) = matches.value_of(RANGE) {
+            RevisionSpec::Range(range.to_string())
+        } else if let Some(branches) = matches.values_of(BRANCHES) {
+            RevisionSpec::Branches(branches.map(|b| b.to_string()).collect())
+        } else if matches.is_present(ALL) {
+            RevisionSpec::All
+        } else {
+            RevisionSpec::Head
+        }
+    }
+
+    /// `--since`, `--until`, `--grep` and `--path` as a filter. `None` if none of them is specified.
+    fn parse_predicates(matches: &ArgMatches) -> Result<Option<ReplaceFilter>, Error> {
+        let context = get_git_context(matches);
+        let parse_date = |key: &str| -> Result<Option<i64>, Error> {
+            match matches.value_of(key) {
+                Some(date) => match git::parse_date(&context, date)? {
+                    Some(time) => Ok(Some(time)),
+                    None => Err(InvalidArguments(format!("invalid date: {}", date)).into()),
+                },
+                None => Ok(None),
+            }
+        };
+        let (since, until) = (parse_date(SINCE)?, parse_date(UNTIL)?);
+
+        let mut predicates = vec![];
+        if since.is_some() || until.is_some() {
+            predicates.push(match matches.value_of(DATE_TYPE) {
+                Some(DATE_TYPE_COMMITTER) => ReplaceFilter::CommitterDate { since, until },
+                _ => ReplaceFilter::AuthorDate { since, until },
+            });
+        }
+        if let Some(regex) = matches.value_of(GREP) {
+            predicates.push(ReplaceFilter::Message(regex::Regex::new(regex)?));
+        }
+        if let Some(pathspecs) = matches.values_of(PATH) {
+            let paths = pathspecs.map(|pathspec| ReplaceFilter::Path(pathspec.to_string()));
+            predicates.extend(paths.reduce(ReplaceFilter::or));
+        }
+        Ok(predicates.into_iter().reduce(ReplaceFilter::and))
+    }
 
-        let new_author = match (
-            matches.value_of(NEW_NAME_KEY),
-            matches.value_of(NEW_EMAIL_KEY),
-        ) {
-            (None, None) => git::get_author(None)?,
-            (name, email) => Author::new(name, email)?,
+    /// Restrict the rules to the commits which match `--since`, `--until`, `--grep` and `--path`.
+    fn restrict_rules(
+        matches: &ArgMatches,
+        rules: Vec<(ReplaceFilter, ReplaceTarget)>,
+    ) -> Result<Vec<(ReplaceFilter, ReplaceTarget)>, Error> {
+        let predicate = match parse_predicates(matches)? {
+            Some(predicate) => predicate,
+            None => return Ok(rules),
         };
+        if output::Format::of(matches).is_text() {
+            println!("only commits: {}", predicate);
+        }
+        Ok(rules
+            .into_iter()
+            .map(|(filter, target)| (filter.and(predicate.clone()), target))
+            .collect())
+    }
 
-        git::replace_simple(old_author, new_author).map_err(|e| e.into())
+    /// `--set-name` and `--set-email`. `None` if neither is specified.
+    fn parse_template(matches: &ArgMatches) -> Result<Option<IdentityTemplate>, Error> {
+        match (matches.value_of(SET_NAME), matches.value_of(SET_EMAIL)) {
+            (None, None) => Ok(None),
+            (name, email) => Ok(Some(IdentityTemplate::new(name, email)?)),
+        }
+    }
+
+    fn parse_engine(matches: &ArgMatches) -> RewriteEngine {
+        RewriteEngine::VARIANTS
+            .iter()
+            .find(|engine| matches.value_of(ENGINE) == Some(&engine.to_string()))
+            .cloned()
+            .unwrap_or_default()
     }
 
     // Option<Values> to Result<Option<Author>, Error>
     fn values_to_author(values: Option<clap::Values>) -> Result<Option<Author>, Error> {
-        if let Some(mut values) = values {
-            let author = Author::new(values.next(), values.next())?;
-            Ok(Some(author))
+        values.map(values_to_one_author).transpose()
+    }
+
+    fn parse_pattern(matches: &ArgMatches) -> Result<Option<IdentityMatcher>, Error> {
+        use option::detail::*;
+
+        let matcher = if let Some(name) = matches.value_of(FILTER_NAME) {
+            IdentityMatcher::Name(name.to_string())
+        } else if let Some(email) = matches.value_of(FILTER_EMAIL) {
+            IdentityMatcher::Email(email.to_string())
+        } else if let Some(email) = matches.value_of(FILTER_EMAIL_IGNORE_CASE) {
+            IdentityMatcher::EmailIgnoreCase(email.to_string())
+        } else if let Some(domain) = matches.value_of(FILTER_EMAIL_DOMAIN) {
+            IdentityMatcher::email_domain(domain)
+        } else if let Some(regex) = matches.value_of(FILTER_REGEX) {
+            IdentityMatcher::regex(regex)?
         } else {
-            Ok(None)
-        }
+            return Ok(None);
+        };
+        Ok(Some(matcher))
     }
 
     fn parse_filter(matches: &ArgMatches) -> Result<ReplaceFilter, Error> {
         use option::detail::*;
 
-        let author = values_to_author(matches.values_of(FILTER_AUTHOR))?;
-        let committer = values_to_author(matches.values_of(FILTER_COMMITTER))?;
+        let author = values_to_author(matches.values_of(FILTER_AUTHOR))?.map(IdentityMatcher::from);
+        let committer =
+            values_to_author(matches.values_of(FILTER_COMMITTER))?.map(IdentityMatcher::from);
         let filter_type = matches.value_of(FILTER_TYPE);
         let filter = match (author, committer, filter_type) {
             (Some(author), None, _) => ReplaceFilter::AuthorOnly(author),
@@ -429,18 +1723,42 @@ mod replace {
     fn parse_target(matches: &ArgMatches) -> Result<ReplaceTarget, Error> {
         use option::detail::*;
 
-        let author = values_to_author(matches.values_of(AUTHOR))?.unwrap_or(git::get_author(None)?);
-        let committer =
-            values_to_author(matches.values_of(COMMITTER))?.unwrap_or(git::get_author(None)?);
+        let context = get_git_context(matches);
+        // The author of git is used if it is not given. A template with no field would change nothing.
+        let new_author = |values| -> Result<IdentityTemplate, Error> {
+            let author = match values_to_author(values)? {
+                Some(author) => author,
+                None => git::get_author(&context, None, Role::User)?,
+            };
+            if author.name().is_none() && author.email().is_none() {
+                return Err(InvalidArguments(
+                    "the new author has neither name nor email. Specify it or set `user.name` and `user.email`"
+                        .to_string(),
+                )
+                .into());
+            }
+            Ok(author.into())
+        };
+        let template = parse_template(matches)?;
+        let author = || match &template {
+            Some(template) => Ok(template.clone()),
+            None => new_author(matches.values_of(AUTHOR)),
+        };
+        let committer = || match &template {
+            Some(template) => Ok(template.clone()),
+            None => new_author(matches.values_of(COMMITTER)),
+        };
         let replace_target = matches.value_of(REPLACE_TARGET);
         let target = match replace_target {
-            Some(REPLACE_TARGET_AUTHOR) => ReplaceTarget::Author { new_author: author },
+            Some(REPLACE_TARGET_AUTHOR) => ReplaceTarget::Author {
+                new_author: author()?,
+            },
             Some(REPLACE_TARGET_COMMITTER) => ReplaceTarget::Committer {
-                new_committer: committer,
+                new_committer: committer()?,
             },
             Some(REPLACE_TARGET_AUTHOR_AND_COMMITTER) => ReplaceTarget::AuthorAndCommitter {
-                new_author: author,
-                new_committer: committer,
+                new_author: author()?,
+                new_committer: committer()?,
             },
             _ => {
                 return Err(InvalidArguments(format!(
@@ -455,3 +1773,557 @@ mod replace {
         Ok(target)
     }
 }
+
+mod profile {
+    pub mod option {
+        use lazy_static::lazy_static;
+
+        pub const NAME: &str = "profile";
+        pub const USE: &str = "use";
+
+        pub const ADD: &str = "add";
+        pub const LIST: &str = "list";
+        pub const REMOVE: &str = "remove";
+        pub const SHOW: &str = "show";
+
+        pub const PROFILE_KEY: &str = "profile";
+        pub const FORCE: &str = "force";
+
+        lazy_static! {
+            pub static ref ABOUT: String = format!(
+                "manage named authors. The profiles are saved in `{}`",
+                git_author::git::ProfileStore::default_path()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_else(|| "~/.config/git-author/profiles.toml".to_string())
+            );
+        }
+    }
+
+    use super::*;
+    use git_author::git::{Profile, ProfileStore};
+
+    pub fn profile(matches: &ArgMatches) -> Result<(), Error> {
+        use option::*;
+
+        let mut store = ProfileStore::load_default()?;
+        let format = output::Format::of(matches);
+        if let Some(matches) = matches.subcommand_matches(ADD) {
+            let profile_name = matches.value_of(PROFILE_KEY).unwrap_or_default();
+            let author = values_to_one_author(positional_values(matches, &[NAME_KEY, EMAIL_KEY]))?;
+            store.add(
+                Profile::new(profile_name, author.clone()),
+                matches.is_present(FORCE),
+            )?;
+            store.save()?;
+            if format.is_text() {
+                println!("add profile {}: {}", profile_name, author);
+            }
+            if let Some(profile) = store.get(profile_name) {
+                let updated = git::update_include_file(&get_git_context(matches), &profile)?;
+                if !format.is_text() {
+                    output::print(format, &profile)?;
+                } else if updated {
+                    println!("update the included file of profile {}", profile_name);
+                }
+            }
+        } else if matches.subcommand_matches(LIST).is_some() {
+            let profiles: Vec<_> = store.profiles().collect();
+            if !format.is_text() {
+                return output::print(format, &profiles);
+            }
+            for profile in profiles {
+                println!("{}: {}", profile.name(), profile.author());
+            }
+        } else if let Some(matches) = matches.subcommand_matches(REMOVE) {
+            let profile = store.remove(matches.value_of(PROFILE_KEY).unwrap_or_default())?;
+            store.save()?;
+            if !format.is_text() {
+                return output::print(format, &profile);
+            }
+            println!("remove profile {}: {}", profile.name(), profile.author());
+        } else if let Some(matches) = matches.subcommand_matches(SHOW) {
+            let profile = get_profile(&store, matches.value_of(PROFILE_KEY).unwrap_or_default())?;
+            if !format.is_text() {
+                return output::print(format, &profile);
+            }
+            println!("{}", profile.author());
+        }
+        Ok(())
+    }
+
+    pub fn use_profile(matches: &ArgMatches) -> Result<(), Error> {
+        let store = ProfileStore::load_default()?;
+        let profile = get_profile(
+            &store,
+            matches.value_of(option::PROFILE_KEY).unwrap_or_default(),
+        )?;
+        let config_file_location =
+            get_config_file_location(matches).unwrap_or(ConfigFileLocation::Local);
+        let context = get_git_context(matches);
+        confirm_worktree_config(&context, Some(&config_file_location))?;
+        let role = get_role(matches);
+        git::set_author(&context, &config_file_location, role, profile.author())?;
+        let format = output::Format::of(matches);
+        if !format.is_text() {
+            return output::print(
+                format,
+                &ConfigChange {
+                    location: Some(&config_file_location),
+                    role,
+                    author: Some(profile.author()),
+                    profile: Some(profile.name()),
+                },
+            );
+        }
+        println!(
+            "{} (profile: {})",
+            set_message(&config_file_location, role, profile.author()),
+            profile.name()
+        );
+        Ok(())
+    }
+
+    pub fn get_profile(store: &ProfileStore, name: &str) -> Result<Profile, ProfileError> {
+        store
+            .get(name)
+            .ok_or_else(|| ProfileError::NotFound(name.to_string()))
+    }
+}
+
+mod rule {
+    pub mod option {
+        pub const NAME: &str = "rule";
+
+        pub const ADD: &str = "add";
+        pub const LIST: &str = "list";
+        pub const REMOVE: &str = "remove";
+
+        pub const GITDIR: &str = "gitdir";
+        pub const GITDIR_HELP: &str = "directory of the repositories. e.g. `~/work/`. \
+             If it ends with `/`, all repositories under the directory are matched.";
+
+        pub const ABOUT: &str =
+            "use a profile automatically in the repositories under a directory \
+             with `[includeIf \"gitdir:...\"]` of the global config file";
+    }
+
+    use super::*;
+    use git_author::git::ProfileStore;
+
+    pub fn rule(matches: &ArgMatches) -> Result<(), Error> {
+        use option::*;
+
+        let context = get_git_context(matches);
+        let format = output::Format::of(matches);
+        if let Some(matches) = matches.subcommand_matches(ADD) {
+            let store = ProfileStore::load_default()?;
+            let profile = profile::get_profile(
+                &store,
+                matches
+                    .value_of(profile::option::PROFILE_KEY)
+                    .unwrap_or_default(),
+            )?;
+            let rule = git::add_include_rule(
+                &context,
+                matches.value_of(GITDIR).unwrap_or_default(),
+                &profile,
+            )?;
+            if !format.is_text() {
+                return output::print(format, &rule);
+            }
+            println!("add rule {}: {}", rule, profile.author());
+        } else if matches.subcommand_matches(LIST).is_some() {
+            let rules = git::include_rules(&context)?;
+            if !format.is_text() {
+                return output::print(format, &rules);
+            }
+            for rule in rules {
+                println!("{} ({})", rule, rule.path().display());
+            }
+        } else if let Some(matches) = matches.subcommand_matches(REMOVE) {
+            let rules =
+                git::remove_include_rule(&context, matches.value_of(GITDIR).unwrap_or_default())?;
+            if !format.is_text() {
+                return output::print(format, &rules);
+            }
+            for rule in rules {
+                println!("remove rule {}", rule);
+            }
+        }
+        Ok(())
+    }
+}
+
+mod mailmap {
+    pub mod option {
+        pub const NAME: &str = "mailmap";
+
+        pub const SUGGEST: &str = "suggest";
+        pub const ADD: &str = "add";
+
+        pub const RANGE: &str = "range";
+        pub const ALL: &str = "all";
+        pub const WRITE: &str = "write";
+        pub const MAILMAP_PATH: &str = "path";
+        pub const OLD_NAME_KEY: &str = "old-name";
+        pub const OLD_EMAIL_KEY: &str = "old-email";
+        pub const NEW_NAME_KEY: &str = "new-name";
+        pub const NEW_EMAIL_KEY: &str = "new-email";
+
+        pub const ABOUT: &str =
+            "fix the authors and committers shown by git with `.mailmap` without rewriting history";
+    }
+
+    use super::*;
+    use git_author::git::{Mailmap, MailmapEntry};
+    use std::path::PathBuf;
+
+    pub fn mailmap(matches: &ArgMatches) -> Result<(), Error> {
+        use option::*;
+
+        if let Some(matches) = matches.subcommand_matches(SUGGEST) {
+            let path = get_path(matches)?;
+            let mailmap = if path.exists() {
+                Mailmap::load(&path)?
+            } else {
+                Mailmap::default()
+            };
+            let revision = match matches.value_of(RANGE) {
+                Some(range) => RevisionSpec::Range(range.to_string()),
+                None if matches.is_present(ALL) => RevisionSpec::All,
+                None => RevisionSpec::Head,
+            };
+            let entries = git::suggest_mailmap(&get_git_context(matches), &mailmap, &revision)?;
+            let format = output::Format::of(matches);
+            for entry in &entries {
+                if matches.is_present(WRITE) {
+                    Mailmap::append(&path, entry)?;
+                }
+                if format.is_text() {
+                    println!("{}", entry);
+                }
+            }
+            if !format.is_text() {
+                output::print(format, &entries)?;
+            } else if matches.is_present(WRITE) {
+                println!("appended {} entries to {}", entries.len(), path.display());
+            }
+        } else if let Some(matches) = matches.subcommand_matches(ADD) {
+            let path = get_path(matches)?;
+            let authors = values_to_authors(positional_values(
+                matches,
+                &[OLD_NAME_KEY, OLD_EMAIL_KEY, NEW_NAME_KEY, NEW_EMAIL_KEY],
+            ))?;
+            let (old, new) = match authors.as_slice() {
+                [old, new] => (old, new),
+                _ => {
+                    return Err(InvalidArguments(format!(
+                        "expected the old author and the new author. {}",
+                        AUTHOR_VALUE_HELP
+                    ))
+                    .into())
+                }
+            };
+            let entry = MailmapEntry::from_authors(new, old)
+                .ok_or_else(|| InvalidArguments(format!("{} has no email", old)))?;
+            let added = Mailmap::append(&path, &entry)?;
+            let format = output::Format::of(matches);
+            if !format.is_text() {
+                return output::print(format, &MailmapAddResult { entry, added });
+            }
+            if added {
+                println!("add {}", entry);
+            } else {
+                println!("{} already has {}", path.display(), entry);
+            }
+        }
+        Ok(())
+    }
+
+    /// `mailmap add` for `--format json` and `--format tsv`
+    #[derive(serde::Serialize)]
+    struct MailmapAddResult {
+        entry: MailmapEntry,
+        /// `false` if the file already has the entry
+        added: bool,
+    }
+
+    fn get_path(matches: &ArgMatches) -> Result<PathBuf, Error> {
+        match matches.value_of_os(option::MAILMAP_PATH) {
+            Some(path) => Ok(path.into()),
+            None => git::default_mailmap_path(&get_git_context(matches)).ok_or_else(|| {
+                InvalidArguments("not in a working tree. Specify the path.".to_string()).into()
+            }),
+        }
+    }
+}
+
+mod stats {
+    pub mod option {
+        pub const NAME: &str = "stats";
+
+        pub const RANGE: &str = "range";
+        pub const ALL: &str = "all";
+        pub const SORT: &str = "sort";
+        pub const REVERSE: &str = "reverse";
+        pub const FILTER: &str = "filter";
+        pub const MISMATCHED: &str = "mismatched";
+
+        pub const SORT_COMMITS: &str = "commits";
+        pub const SORT_NAME: &str = "name";
+        pub const SORT_EMAIL: &str = "email";
+        pub const SORT_FIRST: &str = "first";
+        pub const SORT_LAST: &str = "last";
+        pub const SORT_KEYS: [&str; 5] =
+            [SORT_COMMITS, SORT_NAME, SORT_EMAIL, SORT_FIRST, SORT_LAST];
+
+        pub const ABOUT: &str = "list the authors and committers in the history with their commits";
+    }
+
+    use super::*;
+    use git_author::git::IdentityStats;
+
+    pub fn stats(matches: &ArgMatches) -> Result<(), Error> {
+        use option::*;
+
+        let revision = match matches.value_of(RANGE) {
+            Some(range) => RevisionSpec::Range(range.to_string()),
+            None if matches.is_present(ALL) => RevisionSpec::All,
+            None => RevisionSpec::Head,
+        };
+        let matcher = matches
+            .value_of(FILTER)
+            .map(IdentityMatcher::regex)
+            .transpose()?;
+
+        let mut stats: Vec<_> = git::identity_stats(&get_git_context(matches), &revision)?
+            .into_iter()
+            .filter(|s| matcher.as_ref().is_none_or(|m| m.matches(s.identity())))
+            .filter(|s| !matches.is_present(MISMATCHED) || s.mismatch_count() > 0)
+            .collect();
+        let text = |field: &Option<String>| field.clone().unwrap_or_default().to_lowercase();
+        match matches.value_of(SORT) {
+            Some(SORT_NAME) => stats.sort_by_key(|s| text(s.identity().name())),
+            Some(SORT_EMAIL) => stats.sort_by_key(|s| text(s.identity().email())),
+            Some(SORT_FIRST) => stats.sort_by_key(|s| s.first_time()),
+            Some(SORT_LAST) => stats.sort_by_key(|s| s.last_time()),
+            _ => stats.sort_by_key(|s| std::cmp::Reverse(s.commit_count())),
+        }
+        if matches.is_present(REVERSE) {
+            stats.reverse();
+        }
+
+        let format = output::Format::of(matches);
+        if !format.is_text() {
+            return output::print(format, &stats);
+        }
+        print_stats(&stats);
+        Ok(())
+    }
+
+    fn print_stats(stats: &[IdentityStats]) {
+        println!(
+            "{:>7} {:>7} {:>9} {:>8}  {:<23}  {:<23}  identity",
+            "commits", "author", "committer", "mismatch", "first", "last"
+        );
+        for s in stats {
+            println!(
+                "{:>7} {:>7} {:>9} {:>8}  {:<23}  {:<23}  {}",
+                s.commit_count(),
+                s.author_count(),
+                s.committer_count(),
+                s.mismatch_count(),
+                s.first_time_text(),
+                s.last_time_text(),
+                s.identity()
+            );
+        }
+        println!("{} identities", stats.len());
+    }
+}
+
+mod duplicates {
+    pub mod option {
+        pub const NAME: &str = "duplicates";
+
+        pub const RANGE: &str = "range";
+        pub const ALL: &str = "all";
+        pub const EMIT: &str = "emit";
+
+        pub const EMIT_MAP: &str = "map";
+        pub const EMIT_MAILMAP: &str = "mailmap";
+        pub const EMIT_FORMATS: [&str; 2] = [EMIT_MAP, EMIT_MAILMAP];
+
+        pub const ABOUT: &str = "find authors and committers which are probably the same person";
+    }
+
+    use super::*;
+    use git_author::git::{IdentityCluster, ReplaceMap};
+
+    pub fn duplicates(matches: &ArgMatches) -> Result<(), Error> {
+        use option::*;
+
+        let revision = match matches.value_of(RANGE) {
+            Some(range) => RevisionSpec::Range(range.to_string()),
+            None if matches.is_present(ALL) => RevisionSpec::All,
+            None => RevisionSpec::Head,
+        };
+        let clusters = git::find_duplicates(&get_git_context(matches), &revision)?;
+        let format = output::Format::of(matches);
+
+        match matches.value_of(EMIT) {
+            Some(EMIT_MAP) => {
+                let entries = clusters.iter().flat_map(|c| c.to_map_entries()).collect();
+                print!("{}", ReplaceMap::new(entries).to_toml()?);
+            }
+            Some(EMIT_MAILMAP) => {
+                for entry in clusters.iter().flat_map(|c| c.to_mailmap_entries()) {
+                    println!("{}", entry);
+                }
+            }
+            _ if !format.is_text() => output::print(format, &clusters)?,
+            _ => print_clusters(&clusters),
+        }
+        Ok(())
+    }
+
+    fn print_clusters(clusters: &[IdentityCluster]) {
+        if clusters.is_empty() {
+            println!("no duplicates found");
+            return;
+        }
+        for cluster in clusters {
+            println!(
+                "{} ({} commits)",
+                cluster.canonical(),
+                cluster.commit_count()
+            );
+            for member in cluster.members() {
+                println!("    {:>7}  {}", member.commit_count(), member.identity());
+            }
+            for reason in cluster.reasons() {
+                println!("    because: {}", reason);
+            }
+            println!();
+        }
+        println!("{} clusters", clusters.len());
+    }
+}
+
+mod output {
+    pub mod option {
+        pub const FORMAT: &str = "format";
+
+        pub const TEXT: &str = "text";
+        pub const JSON: &str = "json";
+        pub const TSV: &str = "tsv";
+        pub const FORMATS: [&str; 3] = [TEXT, JSON, TSV];
+    }
+
+    use super::*;
+    use serde::Serialize;
+    use serde_json::Value;
+
+    /// `--format`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Format {
+        /// messages for people
+        Text,
+        /// a JSON value
+        Json,
+        /// a header line and a line for each record. Nested fields are joined with `.`.
+        Tsv,
+    }
+
+    impl Format {
+        pub fn of(matches: &ArgMatches) -> Self {
+            match matches.value_of(option::FORMAT) {
+                Some(option::JSON) => Format::Json,
+                Some(option::TSV) => Format::Tsv,
+                _ => Format::Text,
+            }
+        }
+
+        pub fn is_text(self) -> bool {
+            self == Format::Text
+        }
+    }
+
+    /// Print `value` as JSON or TSV. Nothing is printed with `Format::Text`.
+    /// An array is printed as the records of TSV, and any other value as one record.
+    pub fn print<T: Serialize + ?Sized>(format: Format, value: &T) -> Result<(), Error> {
+        match format {
+            Format::Text => {}
+            Format::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            Format::Tsv => print_tsv(&serde_json::to_value(value)?),
+        }
+        Ok(())
+    }
+
+    fn print_tsv(value: &Value) {
+        let records: Vec<_> = match value {
+            Value::Array(values) => values.iter().map(flatten).collect(),
+            value => vec![flatten(value)],
+        };
+        // Records may have different fields, e.g. the variants of an enum.
+        let mut header: Vec<&str> = vec![];
+        for (key, _) in records.iter().flatten() {
+            if !header.contains(&key.as_str()) {
+                header.push(key);
+            }
+        }
+        println!("{}", header.join("\t"));
+        for record in &records {
+            let fields: Vec<_> = header
+                .iter()
+                .map(|key| {
+                    record
+                        .iter()
+                        .find(|(k, _)| k == key)
+                        .map_or("", |(_, v)| v.as_str())
+                })
+                .collect();
+            println!("{}", fields.join("\t"));
+        }
+    }
+
+    /// Fields of a record. e.g. `{"author": {"name": "foo"}}` -> `author.name`: `foo`
+    /// Arrays in a record are JSON text.
+    fn flatten(value: &Value) -> Vec<(String, String)> {
+        fn flatten_into(key: String, value: &Value, fields: &mut Vec<(String, String)>) {
+            match value {
+                Value::Object(map) => {
+                    for (k, v) in map {
+                        let key = if key.is_empty() {
+                            k.clone()
+                        } else {
+                            format!("{}.{}", key, k)
+                        };
+                        flatten_into(key, v, fields);
+                    }
+                }
+                Value::Null => fields.push((key, String::new())),
+                Value::String(text) => fields.push((key, escape(text))),
+                value => fields.push((key, value.to_string())),
+            }
+        }
+
+        let mut fields = vec![];
+        flatten_into(String::new(), value, &mut fields);
+        // e.g. a tag name in a list
+        if let [(key, _)] = fields.as_mut_slice() {
+            if key.is_empty() {
+                *key = "value".to_string();
+            }
+        }
+        fields
+    }
+
+    /// Escape tabs and line breaks so that a field is in one cell.
+    fn escape(text: &str) -> String {
+        text.replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    }
+}